@@ -8,47 +8,285 @@ extern "C" {
 #include <stddef.h>
 #include <stdbool.h>
 
-typedef struct E2eRSA2K E2eRSA2K;
+/* Opaque handle owning one backend: RSA (e2e_new...) or X25519 (e2e_new_x25519) */
+typedef struct E2eHandle E2eHandle;
+/* Kept so code written against the RSA only SDK still compiles */
+typedef E2eHandle E2eRSA2K;
 
-void hello_from_rust(); //just for me to test C ABI working :)
+/* RSA padding used to wrap the per-message key, decrypt always follows the padding recorded in the message */
+typedef enum e2e_padding {
+    E2E_PADDING_PKCS1V15 = 0,
+    E2E_PADDING_OAEP_SHA256 = 1,
+} e2e_padding;
+
+/* AEAD used by the X25519 backend, decrypt always follows the AEAD recorded in the message */
+typedef enum e2e_aead {
+    E2E_AEAD_CHACHA20POLY1305 = 0,
+    E2E_AEAD_AES256GCM = 1,
+} e2e_aead;
+
+/* Structure of exported keys, PKCS8 exports the public key as SPKI and PKCS1 is RSA only.
+ * Imported keys of any format and encoding are detected */
+typedef enum e2e_key_format {
+    E2E_KEY_FORMAT_PKCS1 = 0,
+    E2E_KEY_FORMAT_PKCS8 = 1,
+} e2e_key_format;
+
+typedef enum e2e_key_encoding {
+    E2E_KEY_ENCODING_PEM = 0,
+    E2E_KEY_ENCODING_DER = 1,
+} e2e_key_encoding;
+
+/* Password based key derivation of encrypted PKCS#8 keys (PBES2 with AES-256-CBC), both are read */
+typedef enum e2e_pkcs8_kdf {
+    E2E_PKCS8_KDF_SCRYPT = 0,
+    E2E_PKCS8_KDF_PBKDF2_SHA256 = 1,
+} e2e_pkcs8_kdf;
+
+/* Signature scheme used by e2e_sign/e2e_verify, message is hashed with SHA-256 */
+typedef enum e2e_signature_scheme {
+    E2E_SIGNATURE_PSS_SHA256 = 0,
+    E2E_SIGNATURE_PKCS1V15_SHA256 = 1,
+} e2e_signature_scheme;
+
+/* Status returned by the SDK functions, E2E_OK on success */
+typedef enum e2e_status {
+    E2E_OK = 0,
+    E2E_ERR_INVALID_ARGUMENT = 1,   /* unknown enum value, invalid option or input */
+    E2E_ERR_NOT_INITIALIZED = 2,    /* keys are not provisioned, call e2e_init first */
+    E2E_ERR_WRONG_PASSWORD = 3,     /* wrong password, corrupted or truncated encrypted file */
+    E2E_ERR_DECRYPTION_FAILED = 4,  /* wrong key pair, corrupted or tampered message */
+    E2E_ERR_MALFORMED_KEY = 5,      /* key is not a valid PEM/DER key of the expected type */
+    E2E_ERR_KEY_PAIR_MISMATCH = 6,  /* private and public keys do not belong to the same pair */
+    E2E_ERR_MESSAGE_TOO_LONG = 7,
+    E2E_ERR_INVALID_SIGNATURE = 8,
+    E2E_ERR_INVALID_FORMAT = 9,     /* not a valid message or encrypted file */
+    E2E_ERR_UNSUPPORTED = 10,       /* key size, format version, or RSA only function on an X25519 instance */
+    E2E_ERR_IO = 11,
+    E2E_ERR_CRYPTO = 12,            /* unexpected failure of the crypto backend */
+    E2E_ERR_INTERNAL = 13,          /* bug in the SDK, it is reported instead of aborting the host application */
+    E2E_ERR_BUFFER_TOO_SMALL = 14,  /* nothing is written, the length argument receives the required size */
+    E2E_ERR_MISSING_PRIVATE_KEY = 15, /* decrypt or sign on an instance initialized with e2e_init_public */
+} e2e_status;
+
+/* Every function checks its pointers and strings: a NULL pointer (except an empty buffer) or a string which is not
+ * valid UTF-8 fails with E2E_ERR_INVALID_ARGUMENT, e2e_free and e2e_buffer_free accept NULL */
+
+void hello_from_rust(); //just for me to test C ABI working :) the greeting goes to the log callback
+
+/* Levels of the messages given to the log callback */
+typedef enum e2e_log_level {
+    E2E_LOG_OFF = 0,
+    E2E_LOG_ERROR = 1,
+    E2E_LOG_WARN = 2,
+    E2E_LOG_INFO = 3,
+    E2E_LOG_DEBUG = 4,
+    E2E_LOG_TRACE = 5,
+} e2e_log_level;
+
+/* message is only valid during the call, it may contain file paths but never keys, passwords or plaintext */
+typedef void (*e2e_log_callback)(e2e_log_level level, const char* message, void* user_data);
+
+/*****************************************************************************************************************
+ *  e2e_set_log_callback function
+ *  brief        Route the SDK log messages to the application
+ *  details      The SDK is silent until a callback is set, it never writes to stdout or stderr
+ *               The callback can be called from any thread which calls the SDK
+ *               The callback may call SDK functions, e2e_set_log_callback included: messages they log are
+ *               given to the callback again (avoid logging from it without end), and e2e_last_error of
+ *               the thread then tells the result of the SDK call which logged once it returns
+ *               A message may still reach the previous callback while another thread replaces it, keep
+ *               its user_data valid until the threads using the SDK are done
+ *  \param[in]   callback: function receiving the messages, NULL to silence the SDK again
+ *  \param[in]   max_level: most verbose level given to callback
+ *  \param[in]   user_data: passed as it is to callback
+ *  \param[out]  -
+ *  \precondition: -
+ *  \reentrant:  FALSE
+ *  \return      E2E_ERR_UNSUPPORTED if the process already uses another Rust logger
+ *               Error code if any
+ ****************************************************************************************************************/
+e2e_status e2e_set_log_callback(e2e_log_callback callback, e2e_log_level max_level, void* user_data);
+
+/*****************************************************************************************************************
+ *  e2e_last_error function
+ *  brief        Get the status of the last SDK call made on the calling thread
+ *  details      Every SDK function sets it, so functions returning a pointer or a size can be checked too
+ *  \param[in]   -
+ *  \param[out]  -
+ *  \precondition: -
+ *  \reentrant:  TRUE
+ *  \return      E2E_OK if the last call succeeded
+ ****************************************************************************************************************/
+e2e_status e2e_last_error(void);
+
+/*****************************************************************************************************************
+ *  e2e_last_error_message function
+ *  brief        Get the detailed message of the last SDK call made on the calling thread
+ *  details      The string is owned by the SDK and stays valid until the next SDK call on the same thread
+ *  \param[in]   -
+ *  \param[out]  -
+ *  \precondition: -
+ *  \reentrant:  TRUE
+ *  \return      Error message, empty string if the last call succeeded
+ ****************************************************************************************************************/
+const char* e2e_last_error_message(void);
 
 /*****************************************************************************************************************
  *  e2e_new function
- *  brief        Create a new E2eRSA2K instance
+ *  brief        Create a new RSA instance, 2048-bit keys and PKCS#1 v1.5 padding
  *  details      -
- *  \param[in]   ptr: point to E2eRSA2K ptr
+ *  \param[in]   ptr: point to E2eHandle ptr
  *  \param[out]  -
  *  \precondition: -
  *  \reentrant:  TRUE
  *  \return      Error code if any
  ****************************************************************************************************************/
-E2eRSA2K* e2e_new();
+E2eHandle* e2e_new();
+
+/*****************************************************************************************************************
+ *  e2e_new_x25519 function
+ *  brief        Create a new X25519 instance (ECDH + HKDF-SHA256 + AEAD)
+ *  details      Faster and smaller messages than RSA, keys are PKCS#8/SPKI PEM files of X25519 type
+ *               RSA only functions (padding, key size, sign, verify) fail on this instance
+ *  \param[in]   aead: E2E_AEAD_CHACHA20POLY1305 or E2E_AEAD_AES256GCM
+ *  \param[out]  -
+ *  \precondition: -
+ *  \reentrant:  TRUE
+ *  \return      NULL if aead is unknown, see e2e_last_error
+ ****************************************************************************************************************/
+E2eHandle* e2e_new_x25519(e2e_aead aead);
+
+/*****************************************************************************************************************
+ *  e2e_new_with_key_size function
+ *  brief        Create a new instance which generates RSA keys of the given size
+ *  details      e2e_new generates 2048-bit keys, e2e_init accepts any size from 2048 to 4096 bits
+ *  \param[in]   bits: 2048, 3072 or 4096
+ *  \param[out]  -
+ *  \precondition: -
+ *  \reentrant:  TRUE
+ *  \return      NULL if key size is not supported, see e2e_last_error
+ ****************************************************************************************************************/
+E2eHandle* e2e_new_with_key_size(size_t bits);
+
+/*****************************************************************************************************************
+ *  e2e_key_size function
+ *  brief        Get the size in bits of the provisioned key pair
+ *  details      -
+ *  \param[in]   ptr: point to E2eHandle ptr
+ *  \param[out]  -
+ *  \precondition: Call e2e_init or e2e_init_withexsecure before using this function
+ *  \reentrant:  TRUE
+ *  \return      RSA key size in bits, 0 if not initialized or not an RSA instance
+ ****************************************************************************************************************/
+size_t e2e_key_size(E2eHandle* ptr);
+
+/*****************************************************************************************************************
+ *  e2e_new_with_padding function
+ *  brief        Create a new RSA instance which encrypts with the given padding
+ *  details      e2e_new uses E2E_PADDING_PKCS1V15
+ *  \param[in]   padding: E2E_PADDING_PKCS1V15 or E2E_PADDING_OAEP_SHA256
+ *  \param[out]  -
+ *  \precondition: -
+ *  \reentrant:  TRUE
+ *  \return      NULL if padding is unknown, see e2e_last_error
+ ****************************************************************************************************************/
+E2eHandle* e2e_new_with_padding(e2e_padding padding);
+
+/*****************************************************************************************************************
+ *  e2e_set_padding function
+ *  brief        Change the padding used by e2e_encrypt
+ *  details      Messages encrypted with OAEP can always be decrypted, messages encrypted with PKCS#1 v1.5
+ *               only by a PKCS#1 v1.5 instance: setting E2E_PADDING_OAEP_SHA256 also turns off
+ *               e2e_set_accept_legacy_padding, call it afterwards to accept both
+ *  \param[in]   ptr: point to E2eHandle ptr
+ *  \param[in]   padding: E2E_PADDING_PKCS1V15 or E2E_PADDING_OAEP_SHA256
+ *  \param[out]  -
+ *  \precondition: -
+ *  \reentrant:  FALSE
+ *  \return      E2E_ERR_INVALID_ARGUMENT if padding is unknown, E2E_ERR_UNSUPPORTED if not an RSA instance
+ ****************************************************************************************************************/
+e2e_status e2e_set_padding(E2eHandle* ptr, e2e_padding padding);
+
+/*****************************************************************************************************************
+ *  e2e_set_accept_legacy_padding function
+ *  brief        Choose whether e2e_decrypt accepts PKCS#1 v1.5 envelopes and legacy raw RSA ciphertexts
+ *  details      On for E2E_PADDING_PKCS1V15 instances, off for E2E_PADDING_OAEP_SHA256 ones since PKCS#1 v1.5
+ *               decryption can be used as a padding oracle. Refused messages fail with E2E_ERR_UNSUPPORTED
+ *  \param[in]   ptr: point to E2eHandle ptr
+ *  \param[in]   accept: true to decrypt PKCS#1 v1.5 messages of peers which cannot use OAEP yet
+ *  \param[out]  -
+ *  \precondition: -
+ *  \reentrant:  FALSE
+ *  \return      E2E_ERR_UNSUPPORTED if not an RSA instance
+ ****************************************************************************************************************/
+e2e_status e2e_set_accept_legacy_padding(E2eHandle* ptr, bool accept);
 
 /*****************************************************************************************************************
  *  e2e_free function
- *  brief        Free your E2eRSA2K object that created
+ *  brief        Free your E2eHandle object that created, whatever its backend
  *  details      -
- *  \param[in]   ptr: point to E2eRSA2K ptr
+ *  \param[in]   ptr: point to E2eHandle ptr
  *  \param[out]  -
  *  \precondition: -
  *  \reentrant:  TRUE
  *  \return      Error code if any
  ****************************************************************************************************************/
-void e2e_free(E2eRSA2K* ptr);
+void e2e_free(E2eHandle* ptr);
 
 /*****************************************************************************************************************
  *  init function
  *  brief        Init necessary configuration for SDK
  *  details      If you already owned private/public key, free to call this function
  *               Otherwise, call generate_pairkey function to generate pair-key firstly
- *  \param[in]   priv_key_path: path to existed private key, expect PEM format
- *  \param[in]   pub_key_path: path to existed public key, expect PEM format
+ *  \param[in]   priv_key_path: path to existed private key, PEM or DER
+ *  \param[in]   pub_key_path: path to existed public key, PEM or DER
  *  \param[out]  -
  *  \precondition: -
  *  \reentrant:  FALSE
  *  \return      Error code if any
  ****************************************************************************************************************/
-bool e2e_init(E2eRSA2K* ptr, const char* priv_key_path, const char* pub_key_path);
+e2e_status e2e_init(E2eHandle* ptr, const char* priv_key_path, const char* pub_key_path);
+
+/*****************************************************************************************************************
+ *  e2e_init_public function
+ *  brief        Init the SDK with the public key of a peer only
+ *  details      Call this function to encrypt messages to a peer or verify its signatures,
+ *               create one instance per peer. e2e_decrypt and e2e_sign fail with E2E_ERR_MISSING_PRIVATE_KEY
+ *  \param[in]   pub_key_path: path to existed public key, PEM or DER
+ *  \param[out]  -
+ *  \precondition: -
+ *  \reentrant:  FALSE
+ *  \return      Error code if any
+ ****************************************************************************************************************/
+e2e_status e2e_init_public(E2eHandle* ptr, const char* pub_key_path);
+
+/*****************************************************************************************************************
+ *  e2e_init_private function
+ *  brief        Init the SDK with its own private key only
+ *  details      The public key is derived from the private key, so no public key file is needed
+ *  \param[in]   priv_key_path: path to existed private key, PEM or DER
+ *  \param[out]  -
+ *  \precondition: -
+ *  \reentrant:  FALSE
+ *  \return      Error code if any
+ ****************************************************************************************************************/
+e2e_status e2e_init_private(E2eHandle* ptr, const char* priv_key_path);
+
+/*****************************************************************************************************************
+ *  e2e_init_from_bytes function
+ *  brief        Same as e2e_init with keys held in memory, nothing is written to disk
+ *  details      e2e_init_public_from_bytes and e2e_init_private_from_bytes mirror e2e_init_public and e2e_init_private
+ *  \param[in]   priv_key, priv_key_len: private key, PKCS#1 (RSA) or PKCS#8, PEM or DER
+ *  \param[in]   pub_key, pub_key_len: public key, PKCS#1 (RSA) or SPKI, PEM or DER
+ *  \param[out]  -
+ *  \precondition: -
+ *  \reentrant:  FALSE
+ *  \return      Error code if any
+ ****************************************************************************************************************/
+e2e_status e2e_init_from_bytes(E2eHandle* ptr, const unsigned char* priv_key, size_t priv_key_len, const unsigned char* pub_key, size_t pub_key_len);
+e2e_status e2e_init_public_from_bytes(E2eHandle* ptr, const unsigned char* pub_key, size_t pub_key_len);
+e2e_status e2e_init_private_from_bytes(E2eHandle* ptr, const unsigned char* priv_key, size_t priv_key_len);
 
 /*****************************************************************************************************************
  *  generate_pairkey function
@@ -61,27 +299,29 @@ bool e2e_init(E2eRSA2K* ptr, const char* priv_key_path, const char* pub_key_path
  *  \reentrant:  FALSE
  *  \return      Error code if any
  ****************************************************************************************************************/
-bool e2e_generate_pairkey(E2eRSA2K* ptr, const char* priv_key_path, const char* pub_key_path);
+e2e_status e2e_generate_pairkey(E2eHandle* ptr, const char* priv_key_path, const char* pub_key_path);
 
 /*****************************************************************************************************************
  *  init_withexsecure function
  *  brief        Init necessary configuration for SDK with secured secret keys
  *  details      If you already owned private/public key, free to call this function
  *               Otherwise, call generate_pairkey_withexsecure function to generate secured pair-key firstly
+ *               The public key may be plain or, as written by older versions, encrypted with the password
  *  \param[in]   priv_key_path: path to secured private key, expect PEM format
- *  \param[in]   pub_key_path: path to secured public key, expect PEM format
+ *  \param[in]   pub_key_path: path to public key, plain or secured, expect PEM format
  *  \param[in]   password: password to secure secret key
  *  \param[out]  -
  *  \precondition: -
  *  \reentrant:  FALSE
  *  \return      Error code if any
  ****************************************************************************************************************/
-bool e2e_init_withexsecure(E2eRSA2K* ptr, const char* priv_key_path, const char* pub_key_path, const char* password);
+e2e_status e2e_init_withexsecure(E2eHandle* ptr, const char* priv_key_path, const char* pub_key_path, const char* password);
 
 /*****************************************************************************************************************
  *  generate_pairkey_withexsecure function
  *  brief        Generate private/public key to input paths with secured secret keys
  *  details      Call this function if you do not have a pair key for setting
+ *               Only the private key is encrypted with the password, the public key stays plain to be shared
  *  \param[in]   priv_key_path: path to private key need to be stored, expect PEM format
  *  \param[in]   pub_key_path: path to public key need to be stored, expect PEM format
  *  \param[in]   password: password to secure secret key
@@ -90,44 +330,223 @@ bool e2e_init_withexsecure(E2eRSA2K* ptr, const char* priv_key_path, const char*
  *  \reentrant:  FALSE
  *  \return      Error code if any
  ****************************************************************************************************************/
-bool e2e_generate_pairkey_withexsecure(E2eRSA2K* ptr, const char* priv_key_path, const char* pub_key_path, const char* password);
+e2e_status e2e_generate_pairkey_withexsecure(E2eHandle* ptr, const char* priv_key_path, const char* pub_key_path, const char* password);
+
+/* Rewrite in plain a public key encrypted with password by an older e2e_generate_pairkey_withexsecure, in the
+ * container or in the legacy headerless format, a plain public key is left as is. Fails with
+ * E2E_ERR_INVALID_ARGUMENT if the decrypted file is not a public key, which is also how a wrong password shows
+ * for the legacy format (it has no integrity check) */
+e2e_status e2e_migrate_exsecure_public_key(const char* pub_key_path, const char* password);
+
+/*****************************************************************************************************************
+ *  e2e_generate_pairkey_encrypted_pkcs8 function
+ *  brief        Generate private/public key to input paths with a password protected private key
+ *  details      The private key is written as standard encrypted PKCS#8 PEM (PBES2, scrypt and AES-256-CBC)
+ *               which OpenSSL reads, the public key stays a plain SPKI PEM file which can be shared
+ *  \param[in]   priv_key_path: path to private key need to be stored
+ *  \param[in]   pub_key_path: path to public key need to be stored
+ *  \param[in]   password: password protecting the private key
+ *  \param[out]  -
+ *  \precondition: -
+ *  \reentrant:  FALSE
+ *  \return      Error code if any
+ ****************************************************************************************************************/
+e2e_status e2e_generate_pairkey_encrypted_pkcs8(E2eHandle* ptr, const char* priv_key_path, const char* pub_key_path, const char* password);
+
+/*****************************************************************************************************************
+ *  e2e_init_encrypted_pkcs8 function
+ *  brief        Init necessary configuration for SDK with a password protected private key
+ *  details      The private key is a standard encrypted PKCS#8 key (PBES2 with scrypt or PBKDF2, AES-256-CBC),
+ *               e.g. written by e2e_generate_pairkey_encrypted_pkcs8 or `openssl genpkey -aes-256-cbc`
+ *  \param[in]   priv_key_path: path to encrypted private key, PEM or DER
+ *  \param[in]   pub_key_path: path to existed public key, not encrypted
+ *  \param[in]   password: password protecting the private key
+ *  \param[out]  -
+ *  \precondition: -
+ *  \reentrant:  FALSE
+ *  \return      Error code if any, E2E_ERR_WRONG_PASSWORD for a wrong password
+ ****************************************************************************************************************/
+e2e_status e2e_init_encrypted_pkcs8(E2eHandle* ptr, const char* priv_key_path, const char* pub_key_path, const char* password);
+
+/* Same as e2e_init_private_from_bytes with an encrypted PKCS#8 private key in PEM or DER */
+e2e_status e2e_init_private_from_encrypted_bytes(E2eHandle* ptr, const unsigned char* priv_key, size_t priv_key_len, const char* password);
 
 /*****************************************************************************************************************
  *  encrypt function
  *  brief        Encrypt message with provisioned public-key
- *  details      Call this function to encrypt message
+ *  details      Call this function to encrypt message of any size
+ *               Output is an envelope: RSA wrapped AES-256-GCM key + encrypted message,
+ *               use e2e_encrypted_size to get its exact size
  *  \param[in]   message: message to encrypt
- *  \param[out]  -
+ *  \param[out]  encrypted_message: buffer receiving the encrypted array
+ *  \param[in,out] encrypted_message_len: capacity of encrypted_message, receives the encrypted size
  *  \precondition: Call e2e_init or e2e_init_withexsecure before using this function
  *  \reentrant:  FALSE
- *  \return      Encrypted array
+ *  \return      E2E_OK, encrypted array is written to encrypted_message
+ *               E2E_ERR_BUFFER_TOO_SMALL, encrypted_message_len receives the required size
  *               Error code if any
  ****************************************************************************************************************/
-bool e2e_encrypt(E2eRSA2K* ptr, const unsigned char* message, size_t message_len, unsigned char* encrypted_message, size_t* encrypted_message_len);
+e2e_status e2e_encrypt(E2eHandle* ptr, const unsigned char* message, size_t message_len, unsigned char* encrypted_message, size_t* encrypted_message_len);
+
+/*****************************************************************************************************************
+ *  e2e_encrypted_size function
+ *  brief        Get the exact size of the output of e2e_encrypt for a message of message_len bytes
+ *  details      Allocate this size before calling e2e_encrypt
+ *               RSA: the size depends on the key, E2E_ERR_NOT_INITIALIZED until a public key is provisioned
+ *               X25519: the size only depends on message_len, no init is needed
+ *  \param[in]   message_len: size of the message to encrypt
+ *  \param[out]  encrypted_size: encrypted size
+ *  \precondition: RSA instances: call e2e_init, e2e_init_public or e2e_init_withexsecure first
+ *  \reentrant:  TRUE
+ *  \return      Error code if any
+ ****************************************************************************************************************/
+e2e_status e2e_encrypted_size(const E2eHandle* ptr, size_t message_len, size_t* encrypted_size);
 
 /*****************************************************************************************************************
  *  decrypt function
  *  brief        Decrypt message with provisioned private-key
  *  details      Call this function to decrypt message
+ *               Decrypted message is always smaller than encrypted_message_len
  *  \param[in]   message: message to decrypt
+ *  \param[out]  decrypted_message: buffer receiving the decrypted array
+ *  \param[in,out] decrypted_message_len: capacity of decrypted_message, receives the decrypted size
+ *  \precondition: Call e2e_init or e2e_init_withexsecure before using this function
+ *  \reentrant:  FALSE
+ *  \return      E2E_OK, decrypted array is written to decrypted_message
+ *               E2E_ERR_BUFFER_TOO_SMALL, decrypted_message_len receives the required size
+ *               Error code if any
+ ****************************************************************************************************************/
+e2e_status e2e_decrypt(E2eHandle* ptr, const unsigned char* encrypted_message, size_t encrypted_message_len, unsigned char* decrypted_message, size_t* decrypted_message_len);
+
+/*****************************************************************************************************************
+ *  e2e_set_signature_scheme function
+ *  brief        Change the scheme used by e2e_sign and e2e_verify
+ *  details      Default is E2E_SIGNATURE_PSS_SHA256, use E2E_SIGNATURE_PKCS1V15_SHA256 for legacy peers
+ *  \param[in]   ptr: point to E2eHandle ptr
+ *  \param[in]   scheme: signature scheme
  *  \param[out]  -
+ *  \precondition: -
+ *  \reentrant:  FALSE
+ *  \return      E2E_ERR_INVALID_ARGUMENT if scheme is unknown, E2E_ERR_UNSUPPORTED if not an RSA instance
+ ****************************************************************************************************************/
+e2e_status e2e_set_signature_scheme(E2eHandle* ptr, e2e_signature_scheme scheme);
+
+/*****************************************************************************************************************
+ *  sign function
+ *  brief        Sign message with provisioned private-key
+ *  details      Signature size equals the RSA modulus size (256 bytes for RSA-2K, see e2e_key_size)
+ *  \param[in]   message: message to sign
+ *  \param[out]  signature: buffer receiving the signature
+ *  \param[in,out] signature_len: capacity of signature, receives the size of the signature
  *  \precondition: Call e2e_init or e2e_init_withexsecure before using this function
  *  \reentrant:  FALSE
- *  \return      Decrypted array
+ *  \return      E2E_ERR_BUFFER_TOO_SMALL, signature_len receives the required size
  *               Error code if any
  ****************************************************************************************************************/
-bool e2e_decrypt(E2eRSA2K* ptr, const unsigned char* encrypted_message, size_t encrypted_message_len, unsigned char* decrypted_message, size_t* decrypted_message_len);
+e2e_status e2e_sign(E2eHandle* ptr, const unsigned char* message, size_t message_len, unsigned char* signature, size_t* signature_len);
+
+/*****************************************************************************************************************
+ *  verify function
+ *  brief        Verify signature of message with provisioned public-key
+ *  details      -
+ *  \param[in]   message: message which was signed
+ *  \param[in]   signature: signature to verify
+ *  \param[out]  -
+ *  \precondition: Call e2e_init or e2e_init_withexsecure before using this function
+ *  \reentrant:  FALSE
+ *  \return      E2E_OK if signature is valid, E2E_ERR_INVALID_SIGNATURE otherwise
+ ****************************************************************************************************************/
+e2e_status e2e_verify(E2eHandle* ptr, const unsigned char* message, size_t message_len, const unsigned char* signature, size_t signature_len);
 
 /*************** Start Secure folder API *************************************************************
  * List all API to protect you secret folder (contain secret info such as keys, cert)
  * The developer's too lazy for writing more detail :( 
  *****************************************************************************************************/
-bool e2e_encrypt_folder(const char* folder_path, const char* password);
-bool e2e_decrypt_folder(const char* folder_path, const char* password);
-size_t e2e_decrypt_file(const char* file_path, const char* password, char *outbuf);
+/* Files already encrypted are skipped by e2e_encrypt_folder, files without header are skipped by e2e_decrypt_folder:
+ * this includes files encrypted by older SDK versions, which stay encrypted, see e2e_migrate_legacy_folder.
+ * A file is only overwritten once it decrypted successfully. Both return the status of the first failed file,
+ * e2e_last_error_message tells how many files failed. */
+e2e_status e2e_encrypt_folder(const char* folder_path, const char* password);
+e2e_status e2e_decrypt_folder(const char* folder_path, const char* password);
+
+/* Rewrite files encrypted by older SDK versions (no header, AES-128-CTR) in the current format, files already in
+ * the current format are left as is. The legacy format has no integrity check: a wrong password cannot be detected
+ * and plaintext files cannot be told apart from legacy ones, only use them on files of an older version.
+ * e2e_migrate_legacy_folder returns the status of the first failed file like e2e_decrypt_folder. */
+e2e_status e2e_migrate_legacy_file(const char* file_path, const char* password);
+e2e_status e2e_migrate_legacy_folder(const char* folder_path, const char* password);
+
+/* How the *_with_options folder functions treat symbolic links */
+typedef enum e2e_symlink_policy {
+    E2E_SYMLINK_SKIP = 0,
+    E2E_SYMLINK_FOLLOW = 1,  /* link target is processed, the link is kept */
+    E2E_SYMLINK_ERROR = 2,   /* every link makes the call fail */
+} e2e_symlink_policy;
+
+/* Select the processed files. Patterns are globs matched against the path relative to folder_path,
+ * '*' also matches '/' so "*.pem" selects pem files at any depth. An excluded directory is not walked. */
+typedef struct e2e_folder_options {
+    bool recursive;
+    const char* const* include;  /* NULL or empty: every file */
+    size_t include_count;
+    const char* const* exclude;
+    size_t exclude_count;
+    int symlinks;                /* e2e_symlink_policy */
+    int max_depth;               /* subdirectory levels walked when recursive, negative: unlimited */
+} e2e_folder_options;
+
+/* Same as e2e_encrypt_folder/e2e_decrypt_folder on the files selected by options, NULL options select direct files only */
+e2e_status e2e_encrypt_folder_with_options(const char* folder_path, const char* password, const e2e_folder_options* options);
+e2e_status e2e_decrypt_folder_with_options(const char* folder_path, const char* password, const e2e_folder_options* options);
+
+/*****************************************************************************************************************
+ *  e2e_decrypt_file function
+ *  brief        Decrypt a file encrypted by the secure folder API without modifying it
+ *  details      Binary safe: the decrypted bytes are copied as they are, zero bytes included,
+ *               outbuf is not NUL terminated
+ *  \param[in]   file_path: path to the encrypted file
+ *  \param[in]   password: password used to encrypt the file
+ *  \param[out]  outbuf: buffer receiving the decrypted bytes
+ *  \param[in,out] outbuf_len: capacity of outbuf, receives the decrypted size
+ *  \precondition: -
+ *  \reentrant:  TRUE
+ *  \return      E2E_ERR_BUFFER_TOO_SMALL, outbuf_len receives the required size
+ *               Error code if any
+ ****************************************************************************************************************/
+e2e_status e2e_decrypt_file(const char* file_path, const char* password, unsigned char* outbuf, size_t* outbuf_len);
+
+/* Deprecated no-op, kept for binaries built against older versions: no function returns memory for it to free,
+ * outputs of the *_alloc functions are freed with e2e_buffer_free */
 void e2e_free_data(void* data);
 /*************** End Secure folder API ***************************************************************/
 
+/*************** Start allocating API ****************************************************************
+ * Same as e2e_encrypt/e2e_decrypt/e2e_decrypt_file, the output is allocated by the SDK so its size
+ * does not have to be known in advance. Free it with e2e_buffer_free, never with free().
+ *****************************************************************************************************/
+typedef struct e2e_buffer {
+    unsigned char* data;
+    size_t len;
+} e2e_buffer;
+
+/* out is set to {NULL, 0} when the call fails */
+e2e_status e2e_encrypt_alloc(const E2eHandle* ptr, const unsigned char* message, size_t message_len, e2e_buffer* out);
+/* Encrypt message once for recipient_count RSA handles (e2e_init_public is enough), each of them decrypts the
+ * result with e2e_decrypt or e2e_decrypt_alloc. Fails with E2E_ERR_UNSUPPORTED for a non RSA handle */
+e2e_status e2e_encrypt_multi_alloc(const E2eHandle* const* recipients, size_t recipient_count, const unsigned char* message, size_t message_len, e2e_buffer* out);
+e2e_status e2e_decrypt_alloc(const E2eHandle* ptr, const unsigned char* encrypted_message, size_t encrypted_message_len, e2e_buffer* out);
+/* Export the keys of an initialized handle, e2e_export_private_key_alloc fails with E2E_ERR_MISSING_PRIVATE_KEY
+ * after e2e_init_public and both fail with E2E_ERR_UNSUPPORTED for PKCS1 on an X25519 handle */
+e2e_status e2e_export_private_key_alloc(const E2eHandle* ptr, e2e_key_format format, e2e_key_encoding encoding, e2e_buffer* out);
+e2e_status e2e_export_public_key_alloc(const E2eHandle* ptr, e2e_key_format format, e2e_key_encoding encoding, e2e_buffer* out);
+/* Export the private key as encrypted PKCS#8, readable by e2e_init_private_from_encrypted_bytes and OpenSSL */
+e2e_status e2e_export_encrypted_private_key_alloc(const E2eHandle* ptr, e2e_key_encoding encoding, const char* password, e2e_pkcs8_kdf kdf, e2e_buffer* out);
+e2e_status e2e_decrypt_file_alloc(const char* file_path, const char* password, e2e_buffer* out);
+
+/* Free the data of buffer and reset it to {NULL, 0}, NULL or an already freed buffer is accepted */
+void e2e_buffer_free(e2e_buffer* buffer);
+/*************** End allocating API ******************************************************************/
+
 #ifdef __cplusplus
 }
 #endif
@@ -1,191 +1,948 @@
-use crate::e2e_implementation::{E2eCyber, E2eRSA2K};
-use crate::exsecure;
+// Exported functions receive raw pointers from C callers, NULL is rejected and other pointers are trusted to be valid
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
+use crate::e2e_implementation::{E2eCyber, E2eRSA, E2eSignature, KeyEncoding, KeyFormat, RsaPadding, SignatureScheme};
+use crate::e2e_x25519::{AeadAlgorithm, E2eX25519};
+use crate::encrypted_key::Pkcs8Kdf;
+use crate::error::E2eError;
+use crate::exsecure::{self, FileOutcome, FolderOptions, FolderReport, KdfParams, SymlinkPolicy};
+use log::{debug, info, LevelFilter, Log, Metadata, Record};
+use std::cell::RefCell;
+use std::fmt::Display;
+use std::panic::{self, AssertUnwindSafe};
 use std::path::Path;
 use std::ffi::{CStr, CString};
-use std::os::raw::{c_char, c_void};
+use std::os::raw::{c_char, c_int, c_void};
+use std::sync::{OnceLock, PoisonError, RwLock};
+
+// Values of e2e_status in e2esdk.h
+const E2E_OK: c_int = 0;
+const E2E_ERR_INVALID_ARGUMENT: c_int = 1;
+const E2E_ERR_NOT_INITIALIZED: c_int = 2;
+const E2E_ERR_WRONG_PASSWORD: c_int = 3;
+const E2E_ERR_DECRYPTION_FAILED: c_int = 4;
+const E2E_ERR_MALFORMED_KEY: c_int = 5;
+const E2E_ERR_KEY_PAIR_MISMATCH: c_int = 6;
+const E2E_ERR_MESSAGE_TOO_LONG: c_int = 7;
+const E2E_ERR_INVALID_SIGNATURE: c_int = 8;
+const E2E_ERR_INVALID_FORMAT: c_int = 9;
+const E2E_ERR_UNSUPPORTED: c_int = 10;
+const E2E_ERR_IO: c_int = 11;
+const E2E_ERR_CRYPTO: c_int = 12;
+const E2E_ERR_INTERNAL: c_int = 13;
+const E2E_ERR_BUFFER_TOO_SMALL: c_int = 14;
+const E2E_ERR_MISSING_PRIVATE_KEY: c_int = 15;
+
+thread_local! {
+    // Status and message of the last call on this thread, read by e2e_last_error/e2e_last_error_message
+    static LAST_ERROR: RefCell<(c_int, CString)> = RefCell::new((E2E_OK, CString::default()));
+}
+
+fn status_from_error(err: &E2eError) -> c_int {
+    match err {
+        E2eError::NotInitialized => E2E_ERR_NOT_INITIALIZED,
+        E2eError::MissingPrivateKey => E2E_ERR_MISSING_PRIVATE_KEY,
+        E2eError::WrongPassword => E2E_ERR_WRONG_PASSWORD,
+        E2eError::DecryptionFailed => E2E_ERR_DECRYPTION_FAILED,
+        E2eError::MalformedKey(_) => E2E_ERR_MALFORMED_KEY,
+        E2eError::KeyPairMismatch => E2E_ERR_KEY_PAIR_MISMATCH,
+        E2eError::MessageTooLong => E2E_ERR_MESSAGE_TOO_LONG,
+        E2eError::InvalidSignature => E2E_ERR_INVALID_SIGNATURE,
+        E2eError::InvalidFormat(_) => E2E_ERR_INVALID_FORMAT,
+        E2eError::Unsupported(_) => E2E_ERR_UNSUPPORTED,
+        E2eError::InvalidInput(_) => E2E_ERR_INVALID_ARGUMENT,
+        E2eError::BufferTooSmall { .. } => E2E_ERR_BUFFER_TOO_SMALL,
+        E2eError::Crypto(_) => E2E_ERR_CRYPTO,
+        E2eError::Io(_) => E2E_ERR_IO,
+    }
+}
+
+// Record status and message for this thread, then return status
+fn set_last_error(status: c_int, message: impl Display) -> c_int {
+    // A C string cannot hold NUL bytes
+    let message = CString::new(message.to_string().replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = (status, message));
+    status
+}
+
+fn report(result: Result<(), E2eError>) -> c_int {
+    match result {
+        Ok(()) => set_last_error(E2E_OK, ""),
+        Err(err) => set_last_error(status_from_error(&err), err),
+    }
+}
+
+// A folder operation fails with the status of its first failed file, the message tells how many failed
+fn report_folder(result: Result<FolderReport, E2eError>) -> c_int {
+    let folder_report = match result {
+        Ok(folder_report) => folder_report,
+        Err(err) => return report(Err(err)),
+    };
+    let failures: Vec<_> = folder_report
+        .files
+        .iter()
+        .filter_map(|file| match &file.outcome {
+            FileOutcome::Failed(err) => Some((&file.path, err)),
+            _ => None,
+        })
+        .collect();
+    match failures.first() {
+        None => report(Ok(())),
+        Some((path, err)) => set_last_error(
+            status_from_error(err),
+            format!("{} of {} files failed, first {}: {}", failures.len(), folder_report.files.len(), path.display(), err),
+        ),
+    }
+}
+
+fn rsa_only_error() -> E2eError {
+    E2eError::Unsupported("operation on a non RSA instance".to_string())
+}
+
+// A panic must never unwind into the C caller, it is reported as E2E_ERR_INTERNAL
+fn catch_panic<T>(on_panic: T, body: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or_else(|payload| {
+        let detail = payload
+            .downcast_ref::<&str>()
+            .map(|detail| detail.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        set_last_error(E2E_ERR_INTERNAL, format!("internal error: {}", detail));
+        on_panic
+    })
+}
+
+// Body of a function returning e2e_status
+fn status_call(body: impl FnOnce() -> Result<(), E2eError>) -> c_int {
+    catch_panic(E2E_ERR_INTERNAL, || report(body()))
+}
+
+// Body of a function returning a value, on_error is returned when it fails (NULL, -1, ...)
+fn value_call<T: Copy>(on_error: T, body: impl FnOnce() -> Result<T, E2eError>) -> T {
+    catch_panic(on_error, || match body() {
+        Ok(value) => {
+            report(Ok(()));
+            value
+        }
+        Err(err) => {
+            report(Err(err));
+            on_error
+        }
+    })
+}
+
+fn null_error(name: &str) -> E2eError {
+    E2eError::InvalidInput(format!("{} is NULL", name))
+}
+
+fn handle_arg<'a>(ptr: *const E2eHandle) -> Result<&'a E2eHandle, E2eError> {
+    unsafe { ptr.as_ref() }.ok_or_else(|| null_error("handle"))
+}
+
+fn handle_mut_arg<'a>(ptr: *mut E2eHandle) -> Result<&'a mut E2eHandle, E2eError> {
+    unsafe { ptr.as_mut() }.ok_or_else(|| null_error("handle"))
+}
+
+fn str_arg<'a>(ptr: *const c_char, name: &str) -> Result<&'a str, E2eError> {
+    if ptr.is_null() {
+        return Err(null_error(name));
+    }
+    unsafe { CStr::from_ptr(ptr) }
+        .to_str()
+        .map_err(|_| E2eError::InvalidInput(format!("{} is not valid UTF-8", name)))
+}
+
+// NULL is accepted for an empty buffer
+fn bytes_arg<'a>(ptr: *const u8, len: usize, name: &str) -> Result<&'a [u8], E2eError> {
+    if len == 0 {
+        return Ok(&[]);
+    }
+    if ptr.is_null() {
+        return Err(null_error(name));
+    }
+    Ok(unsafe { std::slice::from_raw_parts(ptr, len) })
+}
+
+fn out_arg<T>(ptr: *mut T, name: &str) -> Result<*mut T, E2eError> {
+    if ptr.is_null() {
+        return Err(null_error(name));
+    }
+    Ok(ptr)
+}
+
+// Copy data to the caller buffer, out_len gives the capacity of out and receives the size of data
+// When data does not fit nothing is copied and out_len receives the required size
+fn write_out(data: &[u8], out: *mut u8, out_len: *mut usize) -> Result<(), E2eError> {
+    let out_len = unsafe { &mut *out_len };
+    let capacity = *out_len;
+    *out_len = data.len();
+    if data.len() > capacity {
+        return Err(E2eError::BufferTooSmall { required: data.len() });
+    }
+    unsafe { std::ptr::copy_nonoverlapping(data.as_ptr(), out, data.len()) };
+    Ok(())
+}
+
+#[no_mangle]
+pub extern "C" fn e2e_last_error() -> c_int {
+    LAST_ERROR.with(|last_error| last_error.borrow().0)
+}
+
+// Pointer stays valid until the next SDK call on the same thread
+#[no_mangle]
+pub extern "C" fn e2e_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last_error| last_error.borrow().1.as_ptr())
+}
+
+// Values of e2e_log_level in e2esdk.h, they match log::Level
+const E2E_LOG_OFF: c_int = 0;
+const E2E_LOG_ERROR: c_int = 1;
+const E2E_LOG_WARN: c_int = 2;
+const E2E_LOG_INFO: c_int = 3;
+const E2E_LOG_DEBUG: c_int = 4;
+const E2E_LOG_TRACE: c_int = 5;
+
+type LogCallbackFn = extern "C" fn(level: c_int, message: *const c_char, user_data: *mut c_void);
+
+#[derive(Clone, Copy)]
+struct LogCallback {
+    callback: LogCallbackFn,
+    user_data: *mut c_void,
+}
+
+// user_data is only handed back to the callback, the caller makes it usable from any thread
+unsafe impl Send for LogCallback {}
+unsafe impl Sync for LogCallback {}
+
+static LOG_CALLBACK: RwLock<Option<LogCallback>> = RwLock::new(None);
+// Whether CallbackLogger became the logger of the process, it fails when the host installed its own
+static LOGGER_INSTALLED: OnceLock<bool> = OnceLock::new();
+
+// Forward records of the log facade to the callback given to e2e_set_log_callback
+struct CallbackLogger;
+
+impl Log for CallbackLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        // Copied out so the lock is released before the callback runs, which may call back into the SDK
+        let log_callback = *LOG_CALLBACK.read().unwrap_or_else(PoisonError::into_inner);
+        if let Some(log_callback) = log_callback {
+            let message = CString::new(record.args().to_string().replace('\0', " ")).unwrap_or_default();
+            (log_callback.callback)(record.level() as c_int, message.as_ptr(), log_callback.user_data);
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static CALLBACK_LOGGER: CallbackLogger = CallbackLogger;
+
+fn level_filter_from_c(level: c_int) -> Result<LevelFilter, E2eError> {
+    match level {
+        E2E_LOG_OFF => Ok(LevelFilter::Off),
+        E2E_LOG_ERROR => Ok(LevelFilter::Error),
+        E2E_LOG_WARN => Ok(LevelFilter::Warn),
+        E2E_LOG_INFO => Ok(LevelFilter::Info),
+        E2E_LOG_DEBUG => Ok(LevelFilter::Debug),
+        E2E_LOG_TRACE => Ok(LevelFilter::Trace),
+        _ => Err(E2eError::InvalidInput(format!("unknown log level {}", level))),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn e2e_set_log_callback(callback: Option<LogCallbackFn>, max_level: c_int, user_data: *mut c_void) -> c_int {
+    status_call(|| {
+        let max_level = level_filter_from_c(max_level)?;
+        if !*LOGGER_INSTALLED.get_or_init(|| log::set_logger(&CALLBACK_LOGGER).is_ok()) {
+            return Err(E2eError::Unsupported("log callback, another logger is installed in this process".to_string()));
+        }
+        let mut log_callback = LOG_CALLBACK.write().unwrap_or_else(PoisonError::into_inner);
+        *log_callback = callback.map(|callback| LogCallback { callback, user_data });
+        log::set_max_level(if log_callback.is_some() { max_level } else { LevelFilter::Off });
+        Ok(())
+    })
+}
 
 #[no_mangle]
 pub extern "C" fn hello_from_rust() { //testing function
-    println!("Hello from Rust!");
+    info!("Hello from Rust!");
+}
+
+// Opaque handle given to C callers, it owns one of the E2eCyber backends
+pub enum E2eHandle {
+    Rsa(E2eRSA),
+    X25519(E2eX25519),
+}
+
+impl E2eHandle {
+    fn cyber(&self) -> &dyn E2eCyber {
+        match self {
+            E2eHandle::Rsa(instance) => instance,
+            E2eHandle::X25519(instance) => instance,
+        }
+    }
+
+    fn cyber_mut(&mut self) -> &mut dyn E2eCyber {
+        match self {
+            E2eHandle::Rsa(instance) => instance,
+            E2eHandle::X25519(instance) => instance,
+        }
+    }
+
+    // RSA only features (padding, key size, signature) are not available on other backends
+    fn rsa(&self) -> Option<&E2eRSA> {
+        match self {
+            E2eHandle::Rsa(instance) => Some(instance),
+            _ => None,
+        }
+    }
+
+    fn rsa_mut(&mut self) -> Option<&mut E2eRSA> {
+        match self {
+            E2eHandle::Rsa(instance) => Some(instance),
+            _ => None,
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn e2e_new() -> *mut E2eHandle {
+    value_call(std::ptr::null_mut(), || Ok(Box::into_raw(Box::new(E2eHandle::Rsa(E2eRSA::new())))))
+}
+
+// Values of e2e_aead in e2esdk.h
+const E2E_AEAD_CHACHA20POLY1305: c_int = 0;
+const E2E_AEAD_AES256GCM: c_int = 1;
+
+#[no_mangle]
+pub extern "C" fn e2e_new_x25519(aead: c_int) -> *mut E2eHandle {
+    value_call(std::ptr::null_mut(), || {
+        let aead = match aead {
+            E2E_AEAD_CHACHA20POLY1305 => AeadAlgorithm::ChaCha20Poly1305,
+            E2E_AEAD_AES256GCM => AeadAlgorithm::Aes256Gcm,
+            _ => return Err(E2eError::InvalidInput(format!("unknown AEAD {}", aead))),
+        };
+        Ok(Box::into_raw(Box::new(E2eHandle::X25519(E2eX25519::with_aead(aead)))))
+    })
+}
+
+// Values of e2e_padding in e2esdk.h
+const E2E_PADDING_PKCS1V15: c_int = 0;
+const E2E_PADDING_OAEP_SHA256: c_int = 1;
+
+fn padding_from_c(padding: c_int) -> Result<RsaPadding, E2eError> {
+    match padding {
+        E2E_PADDING_PKCS1V15 => Ok(RsaPadding::Pkcs1v15),
+        E2E_PADDING_OAEP_SHA256 => Ok(RsaPadding::OaepSha256),
+        _ => Err(E2eError::InvalidInput(format!("unknown padding {}", padding))),
+    }
+}
+
+// Values of e2e_signature_scheme in e2esdk.h
+const E2E_SIGNATURE_PSS_SHA256: c_int = 0;
+const E2E_SIGNATURE_PKCS1V15_SHA256: c_int = 1;
+
+fn signature_scheme_from_c(scheme: c_int) -> Result<SignatureScheme, E2eError> {
+    match scheme {
+        E2E_SIGNATURE_PSS_SHA256 => Ok(SignatureScheme::PssSha256),
+        E2E_SIGNATURE_PKCS1V15_SHA256 => Ok(SignatureScheme::Pkcs1v15Sha256),
+        _ => Err(E2eError::InvalidInput(format!("unknown signature scheme {}", scheme))),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn e2e_new_with_key_size(bits: usize) -> *mut E2eHandle {
+    value_call(std::ptr::null_mut(), || {
+        let instance = E2eRSA::with_key_size(bits)?;
+        Ok(Box::into_raw(Box::new(E2eHandle::Rsa(instance))))
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn e2e_key_size(ptr: *const E2eHandle) -> usize {
+    value_call(0, || Ok(handle_arg(ptr)?.rsa().and_then(|rsa| rsa.key_size()).unwrap_or(0)))
+}
+
+#[no_mangle]
+pub extern "C" fn e2e_new_with_padding(padding: c_int) -> *mut E2eHandle {
+    value_call(std::ptr::null_mut(), || {
+        let padding = padding_from_c(padding)?;
+        Ok(Box::into_raw(Box::new(E2eHandle::Rsa(E2eRSA::with_padding(padding)))))
+    })
 }
 
 #[no_mangle]
-pub extern "C" fn e2e_new() -> *mut E2eRSA2K {
-    Box::into_raw(Box::new(E2eRSA2K::new()))
+pub extern "C" fn e2e_set_padding(ptr: *mut E2eHandle, padding: c_int) -> c_int {
+    status_call(|| {
+        let rsa = handle_mut_arg(ptr)?.rsa_mut().ok_or_else(rsa_only_error)?;
+        rsa.set_padding(padding_from_c(padding)?);
+        Ok(())
+    })
 }
 
 #[no_mangle]
-pub extern "C" fn e2e_free(ptr: *mut E2eRSA2K) {
+pub extern "C" fn e2e_set_accept_legacy_padding(ptr: *mut E2eHandle, accept: bool) -> c_int {
+    status_call(|| {
+        let rsa = handle_mut_arg(ptr)?.rsa_mut().ok_or_else(rsa_only_error)?;
+        rsa.set_accept_legacy_padding(accept);
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn e2e_free(ptr: *mut E2eHandle) {
     if !ptr.is_null() {
-        unsafe {
+        catch_panic((), || unsafe {
             drop(Box::from_raw(ptr)); // drop the Box
-        }
+        });
     }
 }
 
 #[no_mangle]
 pub extern "C" fn e2e_generate_pairkey(
-    ptr: *mut E2eRSA2K,
+    ptr: *mut E2eHandle,
     priv_key_path: *const c_char,
     pub_key_path: *const c_char
-) -> bool {
-    let instance = unsafe { &mut *ptr };
-    let priv_key_path = unsafe { CStr::from_ptr(priv_key_path).to_str().unwrap() };
-    let pub_key_path = unsafe { CStr::from_ptr(pub_key_path).to_str().unwrap() };
-    let path_priv = Path::new(priv_key_path);
-    let path_pub = Path::new(pub_key_path);
-    instance.generate_pairkey(path_priv, path_pub).is_ok()
+) -> c_int {
+    status_call(|| {
+        let instance = handle_mut_arg(ptr)?;
+        let path_priv = Path::new(str_arg(priv_key_path, "priv_key_path")?);
+        let path_pub = Path::new(str_arg(pub_key_path, "pub_key_path")?);
+        instance.cyber_mut().generate_pairkey(path_priv, path_pub)
+    })
 }
 
 #[no_mangle]
 pub extern "C" fn e2e_init(
-    ptr: *mut E2eRSA2K,
+    ptr: *mut E2eHandle,
     priv_key_path: *const c_char,
     pub_key_path: *const c_char
-) -> bool {
-    let instance = unsafe { &mut *ptr };
-    let priv_key_path = unsafe { CStr::from_ptr(priv_key_path).to_str().unwrap() };
-    let pub_key_path = unsafe { CStr::from_ptr(pub_key_path).to_str().unwrap() };
-    let path_priv = Path::new(priv_key_path);
-    let path_pub = Path::new(pub_key_path);
-    instance.init(path_priv, path_pub).is_ok()
+) -> c_int {
+    status_call(|| {
+        let instance = handle_mut_arg(ptr)?;
+        let path_priv = Path::new(str_arg(priv_key_path, "priv_key_path")?);
+        let path_pub = Path::new(str_arg(pub_key_path, "pub_key_path")?);
+        instance.cyber_mut().init(path_priv, path_pub)
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn e2e_init_public(ptr: *mut E2eHandle, pub_key_path: *const c_char) -> c_int {
+    status_call(|| {
+        let instance = handle_mut_arg(ptr)?;
+        let path_pub = Path::new(str_arg(pub_key_path, "pub_key_path")?);
+        instance.cyber_mut().init_public(path_pub)
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn e2e_init_private(ptr: *mut E2eHandle, priv_key_path: *const c_char) -> c_int {
+    status_call(|| {
+        let instance = handle_mut_arg(ptr)?;
+        let path_priv = Path::new(str_arg(priv_key_path, "priv_key_path")?);
+        instance.cyber_mut().init_private(path_priv)
+    })
+}
+
+// Same as e2e_init/e2e_init_public/e2e_init_private with keys in memory, PEM or DER is detected
+#[no_mangle]
+pub extern "C" fn e2e_init_from_bytes(ptr: *mut E2eHandle, priv_key: *const u8, priv_key_len: usize, pub_key: *const u8, pub_key_len: usize) -> c_int {
+    status_call(|| {
+        let instance = handle_mut_arg(ptr)?;
+        let priv_key = bytes_arg(priv_key, priv_key_len, "priv_key")?;
+        let pub_key = bytes_arg(pub_key, pub_key_len, "pub_key")?;
+        instance.cyber_mut().init_from_bytes(priv_key, pub_key)
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn e2e_init_public_from_bytes(ptr: *mut E2eHandle, pub_key: *const u8, pub_key_len: usize) -> c_int {
+    status_call(|| {
+        let instance = handle_mut_arg(ptr)?;
+        let pub_key = bytes_arg(pub_key, pub_key_len, "pub_key")?;
+        instance.cyber_mut().init_public_from_bytes(pub_key)
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn e2e_init_private_from_bytes(ptr: *mut E2eHandle, priv_key: *const u8, priv_key_len: usize) -> c_int {
+    status_call(|| {
+        let instance = handle_mut_arg(ptr)?;
+        let priv_key = bytes_arg(priv_key, priv_key_len, "priv_key")?;
+        instance.cyber_mut().init_private_from_bytes(priv_key)
+    })
 }
 
 #[no_mangle]
 pub extern "C" fn e2e_generate_pairkey_withexsecure(
-    ptr: *mut E2eRSA2K,
+    ptr: *mut E2eHandle,
     priv_key_path: *const c_char,
     pub_key_path: *const c_char,
     password: *const c_char
-) -> bool {
-    let instance = unsafe { &mut *ptr };
-    let priv_key_path = unsafe { CStr::from_ptr(priv_key_path).to_str().unwrap() };
-    let pub_key_path = unsafe { CStr::from_ptr(pub_key_path).to_str().unwrap() };
-    let path_priv = Path::new(priv_key_path);
-    let path_pub = Path::new(pub_key_path);
-    let password = unsafe { CStr::from_ptr(password).to_str().unwrap() };
-    instance.generate_pairkey_withexsecure(path_priv, path_pub, password).is_ok()
+) -> c_int {
+    status_call(|| {
+        let instance = handle_mut_arg(ptr)?;
+        let path_priv = Path::new(str_arg(priv_key_path, "priv_key_path")?);
+        let path_pub = Path::new(str_arg(pub_key_path, "pub_key_path")?);
+        let password = str_arg(password, "password")?;
+        instance.cyber_mut().generate_pairkey_withexsecure(path_priv, path_pub, password)
+    })
 }
 
 #[no_mangle]
 pub extern "C" fn e2e_init_withexsecure(
-    ptr: *mut E2eRSA2K,
+    ptr: *mut E2eHandle,
+    priv_key_path: *const c_char,
+    pub_key_path: *const c_char,
+    password: *const c_char
+) -> c_int {
+    status_call(|| {
+        let instance = handle_mut_arg(ptr)?;
+        let path_priv = Path::new(str_arg(priv_key_path, "priv_key_path")?);
+        let path_pub = Path::new(str_arg(pub_key_path, "pub_key_path")?);
+        let password = str_arg(password, "password")?;
+        instance.cyber_mut().init_withexsecure(path_priv, path_pub, password)
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn e2e_migrate_exsecure_public_key(pub_key_path: *const c_char, password: *const c_char) -> c_int {
+    status_call(|| {
+        let path_pub = Path::new(str_arg(pub_key_path, "pub_key_path")?);
+        let password = str_arg(password, "password")?;
+        exsecure::migrate_exsecure_public_key(path_pub, password)?;
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn e2e_generate_pairkey_encrypted_pkcs8(
+    ptr: *mut E2eHandle,
     priv_key_path: *const c_char,
     pub_key_path: *const c_char,
     password: *const c_char
-) -> bool {
-    let instance = unsafe { &mut *ptr };
-    let priv_key_path = unsafe { CStr::from_ptr(priv_key_path).to_str().unwrap() };
-    let pub_key_path = unsafe { CStr::from_ptr(pub_key_path).to_str().unwrap() };
-    let path_priv = Path::new(priv_key_path);
-    let path_pub = Path::new(pub_key_path);
-    let password = unsafe { CStr::from_ptr(password).to_str().unwrap() };
-    instance.init_withexsecure(path_priv, path_pub, password).is_ok()
+) -> c_int {
+    status_call(|| {
+        let instance = handle_mut_arg(ptr)?;
+        let path_priv = Path::new(str_arg(priv_key_path, "priv_key_path")?);
+        let path_pub = Path::new(str_arg(pub_key_path, "pub_key_path")?);
+        let password = str_arg(password, "password")?;
+        instance.cyber_mut().generate_pairkey_encrypted_pkcs8(path_priv, path_pub, password)
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn e2e_init_encrypted_pkcs8(
+    ptr: *mut E2eHandle,
+    priv_key_path: *const c_char,
+    pub_key_path: *const c_char,
+    password: *const c_char
+) -> c_int {
+    status_call(|| {
+        let instance = handle_mut_arg(ptr)?;
+        let path_priv = Path::new(str_arg(priv_key_path, "priv_key_path")?);
+        let path_pub = Path::new(str_arg(pub_key_path, "pub_key_path")?);
+        let password = str_arg(password, "password")?;
+        instance.cyber_mut().init_encrypted_pkcs8(path_priv, path_pub, password)
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn e2e_init_private_from_encrypted_bytes(ptr: *mut E2eHandle, priv_key: *const u8, priv_key_len: usize, password: *const c_char) -> c_int {
+    status_call(|| {
+        let instance = handle_mut_arg(ptr)?;
+        let priv_key = bytes_arg(priv_key, priv_key_len, "priv_key")?;
+        let password = str_arg(password, "password")?;
+        instance.cyber_mut().init_private_from_encrypted_bytes(priv_key, password)
+    })
 }
 
 #[no_mangle]
 pub extern "C" fn e2e_encrypt(
-    ptr: *const E2eRSA2K,
+    ptr: *const E2eHandle,
     message: *const u8,
     message_len: usize,
     encrypted_message: *mut u8,
     encrypted_message_len: *mut usize
-) -> bool {
-    let instance = unsafe { &*ptr };
-    let message_slice = unsafe { std::slice::from_raw_parts(message, message_len) };
-    match instance.encrypt(message_slice) {
-        Ok(encrypted) => {
-            unsafe {
-                std::ptr::copy_nonoverlapping(encrypted.as_ptr(), encrypted_message, encrypted.len());
-                *encrypted_message_len = encrypted.len();
-            }
-            true
-        }
-        Err(_) => false, // Return false on failure, TODO: mapping more error code
-    }
+) -> c_int {
+    status_call(|| {
+        let instance = handle_arg(ptr)?;
+        let message_slice = bytes_arg(message, message_len, "message")?;
+        let encrypted_message = out_arg(encrypted_message, "encrypted_message")?;
+        let encrypted_message_len = out_arg(encrypted_message_len, "encrypted_message_len")?;
+        let encrypted = instance.cyber().encrypt(message_slice)?;
+        write_out(&encrypted, encrypted_message, encrypted_message_len)
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn e2e_encrypted_size(ptr: *const E2eHandle, message_len: usize, encrypted_size: *mut usize) -> c_int {
+    status_call(|| {
+        let instance = handle_arg(ptr)?;
+        let encrypted_size = out_arg(encrypted_size, "encrypted_size")?;
+        let size = instance.cyber().encrypted_size(message_len)?;
+        unsafe { *encrypted_size = size };
+        Ok(())
+    })
 }
 
 #[no_mangle]
 pub extern "C" fn e2e_decrypt(
-    ptr: *const E2eRSA2K,
+    ptr: *const E2eHandle,
     encrypted_message: *const u8,
     encrypted_message_len: usize,
     decrypted_message: *mut u8,
     decrypted_message_len: *mut usize
-) -> bool {
-    let instance = unsafe { &*ptr };
-    let encrypted_message_slice = unsafe { std::slice::from_raw_parts(encrypted_message, encrypted_message_len) };
-    match instance.decrypt(encrypted_message_slice) {
-        Ok(dec_msg) => {
-            unsafe {
-                std::ptr::copy_nonoverlapping(dec_msg.as_ptr(), decrypted_message, dec_msg.len());
-                *decrypted_message_len = dec_msg.len();
-            }
-            true
-        },
-        Err(_) => false, // Return false on failure, TODO: mapping more error code
-    }
+) -> c_int {
+    status_call(|| {
+        let instance = handle_arg(ptr)?;
+        let encrypted_message_slice = bytes_arg(encrypted_message, encrypted_message_len, "encrypted_message")?;
+        let decrypted_message = out_arg(decrypted_message, "decrypted_message")?;
+        let decrypted_message_len = out_arg(decrypted_message_len, "decrypted_message_len")?;
+        let dec_msg = instance.cyber().decrypt(encrypted_message_slice)?;
+        write_out(&dec_msg, decrypted_message, decrypted_message_len)
+    })
 }
 
 #[no_mangle]
-pub extern "C" fn e2e_encrypt_folder(folder_path: *const c_char, password: *const c_char) -> bool {
-    let folder_path = unsafe { CStr::from_ptr(folder_path).to_str().unwrap() };
-    let password = unsafe { CStr::from_ptr(password).to_str().unwrap() };
+pub extern "C" fn e2e_set_signature_scheme(ptr: *mut E2eHandle, scheme: c_int) -> c_int {
+    status_call(|| {
+        let rsa = handle_mut_arg(ptr)?.rsa_mut().ok_or_else(rsa_only_error)?;
+        rsa.set_signature_scheme(signature_scheme_from_c(scheme)?);
+        Ok(())
+    })
+}
 
-    let result = exsecure::encrypt_folder(Path::new(folder_path), password);
+#[no_mangle]
+pub extern "C" fn e2e_sign(
+    ptr: *const E2eHandle,
+    message: *const u8,
+    message_len: usize,
+    signature: *mut u8,
+    signature_len: *mut usize
+) -> c_int {
+    status_call(|| {
+        let rsa = handle_arg(ptr)?.rsa().ok_or_else(rsa_only_error)?;
+        let message_slice = bytes_arg(message, message_len, "message")?;
+        let signature = out_arg(signature, "signature")?;
+        let signature_len = out_arg(signature_len, "signature_len")?;
+        let sig = rsa.sign(message_slice)?;
+        write_out(&sig, signature, signature_len)
+    })
+}
 
-    println!("path {:?} result e2e_encrypt_folder {:?}", folder_path, result);
+#[no_mangle]
+pub extern "C" fn e2e_verify(
+    ptr: *const E2eHandle,
+    message: *const u8,
+    message_len: usize,
+    signature: *const u8,
+    signature_len: usize
+) -> c_int {
+    status_call(|| {
+        let rsa = handle_arg(ptr)?.rsa().ok_or_else(rsa_only_error)?;
+        let message_slice = bytes_arg(message, message_len, "message")?;
+        let signature_slice = bytes_arg(signature, signature_len, "signature")?;
+        rsa.verify(message_slice, signature_slice)
+    })
+}
 
-    match result {
-        Ok(_) => true,
-        Err(_) => false, // Return false on failure, TODO: mapping more error code
-    }
+#[no_mangle]
+pub extern "C" fn e2e_encrypt_folder(folder_path: *const c_char, password: *const c_char) -> c_int {
+    catch_panic(E2E_ERR_INTERNAL, || {
+        let result = str_arg(folder_path, "folder_path").and_then(|folder_path| {
+            let password = str_arg(password, "password")?;
+            let result = exsecure::encrypt_folder(Path::new(folder_path), password);
+            debug!("path {:?} result e2e_encrypt_folder {:?}", folder_path, result);
+            result
+        });
+        report_folder(result)
+    })
 }
 
 #[no_mangle]
-pub extern "C" fn e2e_decrypt_folder(folder_path: *const c_char, password: *const c_char) -> bool {
-    let folder_path = unsafe { CStr::from_ptr(folder_path).to_str().unwrap() };
-    let password = unsafe { CStr::from_ptr(password).to_str().unwrap() };
+pub extern "C" fn e2e_decrypt_folder(folder_path: *const c_char, password: *const c_char) -> c_int {
+    catch_panic(E2E_ERR_INTERNAL, || {
+        let result = str_arg(folder_path, "folder_path").and_then(|folder_path| {
+            let password = str_arg(password, "password")?;
+            let result = exsecure::decrypt_folder(Path::new(folder_path), password);
+            debug!("path {:?} result e2e_decrypt_folder {:?}", folder_path, result);
+            result
+        });
+        report_folder(result)
+    })
+}
 
-    let result = exsecure::decrypt_folder(Path::new(folder_path), password);
+// Mirror of e2e_folder_options in e2esdk.h
+#[repr(C)]
+pub struct E2eFolderOptions {
+    pub recursive: bool,
+    pub include: *const *const c_char,
+    pub include_count: usize,
+    pub exclude: *const *const c_char,
+    pub exclude_count: usize,
+    pub symlinks: c_int,
+    pub max_depth: c_int,
+}
 
-    println!("path {:?} result e2e_decrypt_folder {:?}", folder_path, result);
+// Values of e2e_symlink_policy in e2esdk.h
+const E2E_SYMLINK_SKIP: c_int = 0;
+const E2E_SYMLINK_FOLLOW: c_int = 1;
+const E2E_SYMLINK_ERROR: c_int = 2;
 
-    match result {
-        Ok(_) => true,
-        Err(_) => false, // Return false on failure, TODO: mapping more error code
+fn patterns_from_c(patterns: *const *const c_char, count: usize, name: &str) -> Result<Vec<String>, E2eError> {
+    if count == 0 {
+        return Ok(Vec::new());
     }
+    if patterns.is_null() {
+        return Err(null_error(name));
+    }
+    let patterns = unsafe { std::slice::from_raw_parts(patterns, count) };
+    patterns
+        .iter()
+        .map(|&pattern| str_arg(pattern, name).map(String::from))
+        .collect()
+}
+
+// NULL options select the defaults
+fn folder_options_from_c(options: *const E2eFolderOptions) -> Result<FolderOptions, E2eError> {
+    if options.is_null() {
+        return Ok(FolderOptions::default());
+    }
+    let options = unsafe { &*options };
+    let symlinks = match options.symlinks {
+        E2E_SYMLINK_SKIP => SymlinkPolicy::Skip,
+        E2E_SYMLINK_FOLLOW => SymlinkPolicy::Follow,
+        E2E_SYMLINK_ERROR => SymlinkPolicy::Error,
+        policy => return Err(E2eError::InvalidInput(format!("unknown symlink policy {}", policy))),
+    };
+    Ok(FolderOptions {
+        recursive: options.recursive,
+        include: patterns_from_c(options.include, options.include_count, "include pattern")?,
+        exclude: patterns_from_c(options.exclude, options.exclude_count, "exclude pattern")?,
+        symlinks,
+        max_depth: usize::try_from(options.max_depth).ok(),
+    })
 }
 
 #[no_mangle]
-pub extern "C" fn e2e_decrypt_file(file_path: *const c_char, password: *const c_char, outbuf: *mut c_char) -> i64 {
-    let file_path = unsafe { CStr::from_ptr(file_path).to_str().unwrap() };
-    let password = unsafe { CStr::from_ptr(password).to_str().unwrap() };
-    let result = exsecure::decrypt_file(Path::new(file_path), password);
-    println!("path {:?} result e2e_decrypt_file {:?}", file_path, result);
-    match result {
-        Ok(decrypted_data) => {
-            // Convert decrypted data to a CString
-            let c_string = CString::new(decrypted_data).expect("CString::new failed");
+pub extern "C" fn e2e_encrypt_folder_with_options(folder_path: *const c_char, password: *const c_char, options: *const E2eFolderOptions) -> c_int {
+    catch_panic(E2E_ERR_INTERNAL, || {
+        let result = str_arg(folder_path, "folder_path").and_then(|folder_path| {
+            let password = str_arg(password, "password")?;
+            let options = folder_options_from_c(options)?;
+            let result = exsecure::encrypt_folder_with_options(Path::new(folder_path), password, &options, &KdfParams::default());
+            debug!("path {:?} result e2e_encrypt_folder_with_options {:?}", folder_path, result);
+            result
+        });
+        report_folder(result)
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn e2e_decrypt_folder_with_options(folder_path: *const c_char, password: *const c_char, options: *const E2eFolderOptions) -> c_int {
+    catch_panic(E2E_ERR_INTERNAL, || {
+        let result = str_arg(folder_path, "folder_path").and_then(|folder_path| {
+            let password = str_arg(password, "password")?;
+            let options = folder_options_from_c(options)?;
+            let result = exsecure::decrypt_folder_with_options(Path::new(folder_path), password, &options);
+            debug!("path {:?} result e2e_decrypt_folder_with_options {:?}", folder_path, result);
+            result
+        });
+        report_folder(result)
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn e2e_migrate_legacy_file(file_path: *const c_char, password: *const c_char) -> c_int {
+    status_call(|| {
+        let file_path = str_arg(file_path, "file_path")?;
+        let password = str_arg(password, "password")?;
+        exsecure::migrate_legacy_file(Path::new(file_path), password)
+    })
+}
 
-            // Copy the decrypted data to the provided buffer
-            unsafe {
-                let outbuf_len = c_string.as_bytes().len();
-                std::ptr::copy_nonoverlapping(c_string.as_ptr(), outbuf as *mut c_char, outbuf_len);
-                //outbuf.add(outbuf_len);
-                outbuf_len as i64
-            }
-        },
-        Err(_) => {
-            -1 // Return -1 on failure, TODO: mapping more error code
-        },
+#[no_mangle]
+pub extern "C" fn e2e_migrate_legacy_folder(folder_path: *const c_char, password: *const c_char) -> c_int {
+    catch_panic(E2E_ERR_INTERNAL, || {
+        let result = str_arg(folder_path, "folder_path").and_then(|folder_path| {
+            let password = str_arg(password, "password")?;
+            let result = exsecure::migrate_legacy_folder(Path::new(folder_path), password);
+            debug!("path {:?} result e2e_migrate_legacy_folder {:?}", folder_path, result);
+            result
+        });
+        report_folder(result)
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn e2e_decrypt_file(file_path: *const c_char, password: *const c_char, outbuf: *mut u8, outbuf_len: *mut usize) -> c_int {
+    status_call(|| {
+        let file_path = str_arg(file_path, "file_path")?;
+        let password = str_arg(password, "password")?;
+        let outbuf = out_arg(outbuf, "outbuf")?;
+        let outbuf_len = out_arg(outbuf_len, "outbuf_len")?;
+        let result = exsecure::decrypt_file(Path::new(file_path), password);
+        debug!("path {:?} result e2e_decrypt_file {:?}", file_path, result.as_ref().map(Vec::len));
+
+        // Raw bytes are copied as they are, files may contain zero bytes (DER keys, PKCS#12, ...)
+        write_out(&result?, outbuf, outbuf_len)
+    })
+}
+
+// Mirror of e2e_buffer in e2esdk.h, data is owned by the SDK until e2e_buffer_free
+#[repr(C)]
+pub struct E2eBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+impl E2eBuffer {
+    fn empty() -> Self {
+        E2eBuffer { data: std::ptr::null_mut(), len: 0 }
+    }
+
+    fn from_vec(data: Vec<u8>) -> Self {
+        // A boxed slice has no spare capacity, so data and len are enough to free it
+        let data = data.into_boxed_slice();
+        let len = data.len();
+        E2eBuffer { data: Box::into_raw(data) as *mut u8, len }
     }
 }
 
+// out is emptied first, so it is always safe to give to e2e_buffer_free even when body fails
+fn alloc_call(out: *mut E2eBuffer, body: impl FnOnce() -> Result<Vec<u8>, E2eError>) -> c_int {
+    status_call(|| {
+        let out = unsafe { out.as_mut() }.ok_or_else(|| null_error("out"))?;
+        *out = E2eBuffer::empty();
+        *out = E2eBuffer::from_vec(body()?);
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn e2e_encrypt_alloc(ptr: *const E2eHandle, message: *const u8, message_len: usize, out: *mut E2eBuffer) -> c_int {
+    alloc_call(out, || {
+        let instance = handle_arg(ptr)?;
+        let message_slice = bytes_arg(message, message_len, "message")?;
+        instance.cyber().encrypt(message_slice)
+    })
+}
+
+// Every recipient is an RSA handle, the result is decrypted by each of them with e2e_decrypt
+#[no_mangle]
+pub extern "C" fn e2e_encrypt_multi_alloc(recipients: *const *const E2eHandle, recipient_count: usize, message: *const u8, message_len: usize, out: *mut E2eBuffer) -> c_int {
+    alloc_call(out, || {
+        if recipients.is_null() {
+            return Err(null_error("recipients"));
+        }
+        let handles = unsafe { std::slice::from_raw_parts(recipients, recipient_count) };
+        let recipients = handles.iter()
+            .map(|&handle| handle_arg(handle)?.rsa().ok_or_else(rsa_only_error))
+            .collect::<Result<Vec<&E2eRSA>, E2eError>>()?;
+        let message_slice = bytes_arg(message, message_len, "message")?;
+        E2eRSA::encrypt_multi(&recipients, message_slice)
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn e2e_decrypt_alloc(ptr: *const E2eHandle, encrypted_message: *const u8, encrypted_message_len: usize, out: *mut E2eBuffer) -> c_int {
+    alloc_call(out, || {
+        let instance = handle_arg(ptr)?;
+        let encrypted_message_slice = bytes_arg(encrypted_message, encrypted_message_len, "encrypted_message")?;
+        instance.cyber().decrypt(encrypted_message_slice)
+    })
+}
+
+// Values of e2e_key_format and e2e_key_encoding in e2esdk.h
+const E2E_KEY_FORMAT_PKCS1: c_int = 0;
+const E2E_KEY_FORMAT_PKCS8: c_int = 1;
+const E2E_KEY_ENCODING_PEM: c_int = 0;
+const E2E_KEY_ENCODING_DER: c_int = 1;
+
+fn key_format_from_c(format: c_int, encoding: c_int) -> Result<(KeyFormat, KeyEncoding), E2eError> {
+    let format = match format {
+        E2E_KEY_FORMAT_PKCS1 => KeyFormat::Pkcs1,
+        E2E_KEY_FORMAT_PKCS8 => KeyFormat::Pkcs8,
+        _ => return Err(E2eError::InvalidInput(format!("unknown key format {}", format))),
+    };
+    let encoding = match encoding {
+        E2E_KEY_ENCODING_PEM => KeyEncoding::Pem,
+        E2E_KEY_ENCODING_DER => KeyEncoding::Der,
+        _ => return Err(E2eError::InvalidInput(format!("unknown key encoding {}", encoding))),
+    };
+    Ok((format, encoding))
+}
+
+#[no_mangle]
+pub extern "C" fn e2e_export_private_key_alloc(ptr: *const E2eHandle, format: c_int, encoding: c_int, out: *mut E2eBuffer) -> c_int {
+    alloc_call(out, || {
+        let instance = handle_arg(ptr)?;
+        let (format, encoding) = key_format_from_c(format, encoding)?;
+        instance.cyber().export_private_key(format, encoding)
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn e2e_export_public_key_alloc(ptr: *const E2eHandle, format: c_int, encoding: c_int, out: *mut E2eBuffer) -> c_int {
+    alloc_call(out, || {
+        let instance = handle_arg(ptr)?;
+        let (format, encoding) = key_format_from_c(format, encoding)?;
+        instance.cyber().export_public_key(format, encoding)
+    })
+}
+
+// Values of e2e_pkcs8_kdf in e2esdk.h
+const E2E_PKCS8_KDF_SCRYPT: c_int = 0;
+const E2E_PKCS8_KDF_PBKDF2_SHA256: c_int = 1;
+
+#[no_mangle]
+pub extern "C" fn e2e_export_encrypted_private_key_alloc(ptr: *const E2eHandle, encoding: c_int, password: *const c_char, kdf: c_int, out: *mut E2eBuffer) -> c_int {
+    alloc_call(out, || {
+        let instance = handle_arg(ptr)?;
+        let (_, encoding) = key_format_from_c(E2E_KEY_FORMAT_PKCS8, encoding)?;
+        let password = str_arg(password, "password")?;
+        let kdf = match kdf {
+            E2E_PKCS8_KDF_SCRYPT => Pkcs8Kdf::Scrypt,
+            E2E_PKCS8_KDF_PBKDF2_SHA256 => Pkcs8Kdf::Pbkdf2Sha256,
+            _ => return Err(E2eError::InvalidInput(format!("unknown PKCS#8 KDF {}", kdf))),
+        };
+        instance.cyber().export_encrypted_private_key(encoding, password, kdf)
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn e2e_decrypt_file_alloc(file_path: *const c_char, password: *const c_char, out: *mut E2eBuffer) -> c_int {
+    alloc_call(out, || {
+        let file_path = str_arg(file_path, "file_path")?;
+        let password = str_arg(password, "password")?;
+        let result = exsecure::decrypt_file(Path::new(file_path), password);
+        debug!("path {:?} result e2e_decrypt_file_alloc {:?}", file_path, result.as_ref().map(Vec::len));
+        result
+    })
+}
+
+// Free a buffer filled by an *_alloc function, it is emptied so freeing it twice is harmless
 #[no_mangle]
-pub extern "C" fn e2e_free_data(data: *mut c_void) {
-    if data.is_null() { return; }
-    unsafe {
-        let _ = Box::from_raw(data as *mut Vec<u8>);
+pub extern "C" fn e2e_buffer_free(buffer: *mut E2eBuffer) {
+    let Some(buffer) = (unsafe { buffer.as_mut() }) else {
+        return;
+    };
+    if !buffer.data.is_null() {
+        catch_panic((), || unsafe {
+            drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(buffer.data, buffer.len)));
+        });
     }
-}
\ No newline at end of file
+    *buffer = E2eBuffer::empty();
+}
+
+// Deprecated, kept so binaries built against older versions still load. No function returns memory for it to
+// free since outputs are written to caller buffers or e2e_buffer, so it does nothing
+#[no_mangle]
+pub extern "C" fn e2e_free_data(_data: *mut c_void) {}
\ No newline at end of file
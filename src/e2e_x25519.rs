@@ -0,0 +1,559 @@
+use hkdf::Hkdf;
+use pkcs8::der::asn1::{BitStringRef, OctetStringRef};
+use pkcs8::der::pem::PemLabel;
+use pkcs8::der::{Decode, Document, Encode, SecretDocument};
+use pkcs8::spki::{AlgorithmIdentifierRef, SubjectPublicKeyInfoRef};
+use pkcs8::{LineEnding, ObjectIdentifier, PrivateKeyInfo};
+use sha2::Sha256;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::str;
+use x25519_dalek::{EphemeralSecret, PublicKey, StaticSecret};
+use crate::*;
+use crate::e2e_implementation::pem_str;
+use crate::encrypted_key;
+use crate::envelope;
+use crate::error::E2eError;
+
+// id-X25519 from RFC 8410, keys are stored as PKCS#8 / SPKI PEM so OpenSSL can read them
+const X25519_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.101.110");
+const X25519_KEY_LEN: usize = 32;
+const HKDF_INFO: &[u8] = b"e2esdk X25519 data key v1";
+
+// AEAD used to encrypt the message with the ECDH derived data key, recorded in every envelope
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AeadAlgorithm {
+    #[default]
+    ChaCha20Poly1305,
+    Aes256Gcm,
+}
+
+pub struct E2eX25519 {
+    isinit: Arc<Mutex<bool>>,
+    privkey: Option<Arc<Mutex<StaticSecret>>>,
+    pubkey: Option<Arc<Mutex<PublicKey>>>,
+    aead: AeadAlgorithm,
+}
+
+/*****************************************************************************************************************
+ * Start trait E2eX25519 implementation for E2eCyber
+ *****************************************************************************************************************/
+impl E2eCyber for E2eX25519 {
+
+/*****************************************************************************************************************
+ *  e2esdk::init function
+ *  brief        Init necessary configuration for SDK
+ *  details      If you already owned private/public key, free to call this function
+ *               Otherwise, call generate_pairkey function to generate pair-key firstly
+ *  \param[in]   priv_key_path: path to existed X25519 private key, PKCS#8, PEM or DER
+ *  \param[in]   pub_key_path: path to existed X25519 public key, SPKI, PEM or DER
+ *  \param[out]  -
+ *  \precondition: -
+ *  \reentrant:  FALSE
+ *  \return      Error code if any
+ ****************************************************************************************************************/
+fn init(&mut self, priv_key_path: &Path, pub_key_path: &Path) -> Result<(), E2eError> {
+    self.init_from_bytes(&fs::read(priv_key_path)?, &fs::read(pub_key_path)?)
+}
+
+/*****************************************************************************************************************
+ *  e2esdk::init_public function
+ *  brief        Init the SDK with the public key of a peer only
+ *  details      Call this function to encrypt messages to a peer, create one instance per peer
+ *               decrypt fails with E2eError::MissingPrivateKey
+ *  \param[in]   pub_key_path: path to existed X25519 public key, SPKI, PEM or DER
+ *  \param[out]  -
+ *  \precondition: -
+ *  \reentrant:  FALSE
+ *  \return      Error code if any
+ ****************************************************************************************************************/
+fn init_public(&mut self, pub_key_path: &Path) -> Result<(), E2eError> {
+    self.init_public_from_bytes(&fs::read(pub_key_path)?)
+}
+
+/*****************************************************************************************************************
+ *  e2esdk::init_private function
+ *  brief        Init the SDK with its own private key only
+ *  details      The public key is derived from the private key, so no public key file is needed
+ *  \param[in]   priv_key_path: path to existed X25519 private key, PKCS#8, PEM or DER
+ *  \param[out]  -
+ *  \precondition: -
+ *  \reentrant:  FALSE
+ *  \return      Error code if any
+ ****************************************************************************************************************/
+fn init_private(&mut self, priv_key_path: &Path) -> Result<(), E2eError> {
+    self.init_private_from_bytes(&fs::read(priv_key_path)?)
+}
+
+/*****************************************************************************************************************
+ *  e2esdk::init_from_bytes function
+ *  brief        Init necessary configuration for SDK from keys held in memory
+ *  details      Same as init without touching the disk, PEM or DER is detected
+ *  \param[in]   priv_key: X25519 private key, PKCS#8 in PEM or DER
+ *  \param[in]   pub_key: X25519 public key, SPKI in PEM or DER
+ *  \param[out]  -
+ *  \precondition: -
+ *  \reentrant:  FALSE
+ *  \return      Error code if any
+ ****************************************************************************************************************/
+fn init_from_bytes(&mut self, priv_key: &[u8], pub_key: &[u8]) -> Result<(), E2eError> {
+    let priv_key = decode_private_key(priv_key)?;
+    let pub_key = decode_public_key(pub_key)?;
+
+    // Verify the key pair, the public key must be derived from the private key
+    if PublicKey::from(&priv_key) != pub_key {
+        return Err(E2eError::KeyPairMismatch);
+    }
+
+    self.set_keys(Some(priv_key), pub_key);
+    Ok(())
+}
+
+// Same as init_public with a SPKI public key in PEM or DER
+fn init_public_from_bytes(&mut self, pub_key: &[u8]) -> Result<(), E2eError> {
+    let pub_key = decode_public_key(pub_key)?;
+    self.set_keys(None, pub_key);
+    Ok(())
+}
+
+// Same as init_private with a PKCS#8 private key in PEM or DER
+fn init_private_from_bytes(&mut self, priv_key: &[u8]) -> Result<(), E2eError> {
+    let priv_key = decode_private_key(priv_key)?;
+    let pub_key = PublicKey::from(&priv_key);
+    self.set_keys(Some(priv_key), pub_key);
+    Ok(())
+}
+
+/*****************************************************************************************************************
+ *  e2esdk::export_private_key function
+ *  brief        Export the provisioned private key
+ *  details      PKCS#1 only exists for RSA, X25519 keys are exported as PKCS#8
+ *  \param[in]   format: KeyFormat::Pkcs8
+ *  \param[in]   encoding: PEM or DER
+ *  \param[out]  -
+ *  \precondition: Call init or init_private before using this function
+ *  \reentrant:  TRUE
+ *  \return      Encoded private key
+ *               Error code if any
+ ****************************************************************************************************************/
+fn export_private_key(&self, format: KeyFormat, encoding: KeyEncoding) -> Result<Vec<u8>, E2eError> {
+    if !self.is_initialized() {
+        return Err(E2eError::NotInitialized);
+    }
+    let priv_key = self.privkey.as_ref().ok_or(E2eError::MissingPrivateKey)?;
+    let priv_key = priv_key.lock().unwrap();
+    check_key_format(format)?;
+    match encoding {
+        KeyEncoding::Pem => encode_private_key_pem(&priv_key).map(String::into_bytes),
+        KeyEncoding::Der => private_key_document(&priv_key).map(|der| der.as_bytes().to_vec()),
+    }
+}
+
+// Export the provisioned public key as SPKI in PEM or DER
+fn export_public_key(&self, format: KeyFormat, encoding: KeyEncoding) -> Result<Vec<u8>, E2eError> {
+    let pub_key = self.pubkey.as_ref().ok_or(E2eError::NotInitialized)?;
+    let pub_key = pub_key.lock().unwrap();
+    check_key_format(format)?;
+    match encoding {
+        KeyEncoding::Pem => encode_public_key_pem(&pub_key).map(String::into_bytes),
+        KeyEncoding::Der => public_key_document(&pub_key).map(Document::into_vec),
+    }
+}
+
+/*****************************************************************************************************************
+ *  e2esdk::init_encrypted_pkcs8 function
+ *  brief        Init necessary configuration for SDK with a password protected private key
+ *  details      The private key is a standard encrypted PKCS#8 key (PBES2 with scrypt or PBKDF2, AES-256-CBC),
+ *               as written by generate_pairkey_encrypted_pkcs8 or `openssl genpkey -aes-256-cbc`
+ *  \param[in]   priv_key_path: path to encrypted private key, PEM or DER
+ *  \param[in]   pub_key_path: path to existed public key, not encrypted
+ *  \param[in]   password: password protecting the private key
+ *  \param[out]  -
+ *  \precondition: -
+ *  \reentrant:  FALSE
+ *  \return      Error code if any, E2eError::WrongPassword for a wrong password
+ ****************************************************************************************************************/
+fn init_encrypted_pkcs8(&mut self, priv_key_path: &Path, pub_key_path: &Path, password: &str) -> Result<(), E2eError> {
+    let priv_der = encrypted_key::decrypt_private_key(&fs::read(priv_key_path)?, password)?;
+    self.init_from_bytes(priv_der.as_bytes(), &fs::read(pub_key_path)?)
+}
+
+/*****************************************************************************************************************
+ *  e2esdk::generate_pairkey_encrypted_pkcs8 function
+ *  brief        Generate private/public key to input paths with a password protected private key
+ *  details      The private key is written as encrypted PKCS#8 PEM (PBES2, scrypt and AES-256-CBC) which OpenSSL
+ *               reads, the public key stays a plain SPKI PEM file which can be shared
+ *  \param[in]   priv_key_path: path to private key need to be stored
+ *  \param[in]   pub_key_path: path to public key need to be stored
+ *  \param[in]   password: password protecting the private key
+ *  \param[out]  -
+ *  \precondition: -
+ *  \reentrant:  FALSE
+ *  \return      Error code if any
+ ****************************************************************************************************************/
+fn generate_pairkey_encrypted_pkcs8(&mut self, priv_key_path: &Path, pub_key_path: &Path, password: &str) -> Result<(), E2eError> {
+    let priv_key = StaticSecret::random_from_rng(rand::thread_rng()); //we can improve random mecha by using HSM instead
+    let pub_key = PublicKey::from(&priv_key);
+
+    let priv_der = private_key_document(&priv_key)?;
+    let priv_pem = encrypted_key::encrypt_private_key(priv_der.as_bytes(), password, Pkcs8Kdf::default(), KeyEncoding::Pem)?;
+    fs::write(priv_key_path, priv_pem)?;
+    fs::write(pub_key_path, encode_public_key_pem(&pub_key)?)?;
+    Ok(())
+}
+
+// Same as init_private_from_bytes with an encrypted PKCS#8 private key in PEM or DER
+fn init_private_from_encrypted_bytes(&mut self, priv_key: &[u8], password: &str) -> Result<(), E2eError> {
+    let priv_der = encrypted_key::decrypt_private_key(priv_key, password)?;
+    self.init_private_from_bytes(priv_der.as_bytes())
+}
+
+// Export the provisioned private key as encrypted PKCS#8, readable by init_private_from_encrypted_bytes and OpenSSL
+fn export_encrypted_private_key(&self, encoding: KeyEncoding, password: &str, kdf: Pkcs8Kdf) -> Result<Vec<u8>, E2eError> {
+    let priv_der = self.export_private_key(KeyFormat::Pkcs8, KeyEncoding::Der)?;
+    encrypted_key::encrypt_private_key(&priv_der, password, kdf, encoding)
+}
+
+/*****************************************************************************************************************
+ *  e2esdk::generate_pairkey function
+ *  brief        Generate private/public key to input paths
+ *  details      Call this function if you do not have a pair key for setting
+ *  \param[in]   priv_key_path: path to private key need to be stored, PKCS#8 PEM format
+ *  \param[in]   pub_key_path: path to public key need to be stored, SPKI PEM format
+ *  \param[out]  -
+ *  \precondition: -
+ *  \reentrant:  FALSE
+ *  \return      Error code if any
+ ****************************************************************************************************************/
+fn generate_pairkey(&mut self, priv_key_path: &Path, pub_key_path: &Path) -> Result<(), E2eError> {
+    let priv_key = StaticSecret::random_from_rng(rand::thread_rng()); //we can improve random mecha by using HSM instead
+    let pub_key = PublicKey::from(&priv_key);
+
+    // Save the private key to the specified path
+    {
+        let priv_pem = encode_private_key_pem(&priv_key)?;
+        let mut file = File::create(priv_key_path)?;
+        file.write_all(priv_pem.as_bytes())?;
+    }
+
+    // Save the public key to the specified path
+    {
+        let pub_pem = encode_public_key_pem(&pub_key)?;
+        let mut file = File::create(pub_key_path)?;
+        file.write_all(pub_pem.as_bytes())?;
+    }
+
+    Ok(())
+}
+
+/*****************************************************************************************************************
+ *  e2esdk::init_withexsecure function
+ *  brief        Init necessary configuration for SDK with secured secret keys
+ *  details      If you already owned private/public key, free to call this function
+ *               Otherwise, call generate_pairkey_withexsecure function to generate secured pair-key firstly
+ *               The public key may be plain or, as written by older versions, encrypted with the password
+ *  \param[in]   priv_key_path: path to secured private key, expect PKCS#8 PEM format
+ *  \param[in]   pub_key_path: path to public key, plain or secured, expect SPKI PEM format
+ *  \param[in]   password: password to secure secret key
+ *  \param[out]  -
+ *  \precondition: -
+ *  \reentrant:  FALSE
+ *  \return      Error code if any
+ ****************************************************************************************************************/
+fn init_withexsecure(&mut self, priv_key_path: &Path, pub_key_path: &Path, password: &str) -> Result<(), E2eError> {
+    let decrypted_priv_key = decrypt_file(priv_key_path, password)?;
+    let pub_key = read_exsecure_public_key(pub_key_path, password)?;
+    self.init_from_bytes(&decrypted_priv_key, &pub_key)
+}
+
+/*****************************************************************************************************************
+ *  e2esdk::generate_pairkey_withexsecure function
+ *  brief        Generate private/public key to input paths with secured secret keys
+ *  details      Call this function if you do not have a pair key for setting
+ *               Only the private key is encrypted with the password, the public key stays plain to be shared
+ *  \param[in]   priv_key_path: path to private key need to be stored, PKCS#8 PEM format
+ *  \param[in]   pub_key_path: path to public key need to be stored, SPKI PEM format
+ *  \param[in]   password: password to secure secret key
+ *  \param[out]  -
+ *  \precondition: -
+ *  \reentrant:  FALSE
+ *  \return      Error code if any
+ ****************************************************************************************************************/
+fn generate_pairkey_withexsecure(&mut self, priv_key_path: &Path, pub_key_path: &Path, password: &str) -> Result<(), E2eError> {
+    let priv_key = StaticSecret::random_from_rng(rand::thread_rng());
+    let pub_key = PublicKey::from(&priv_key);
+
+    // Save the private key to the specified path, encrypted before it reaches the disk
+    let priv_pem = encode_private_key_pem(&priv_key)?;
+    write_encrypted_file(priv_key_path, priv_pem.as_bytes(), password)?;
+
+    // Save the public key to the specified path
+    {
+        let pub_pem = encode_public_key_pem(&pub_key)?;
+        let mut file = File::create(pub_key_path)?;
+        file.write_all(pub_pem.as_bytes())?;
+    }
+
+    Ok(())
+}
+
+/*****************************************************************************************************************
+ *  e2esdk::encrypt function
+ *  brief        Encrypt message with provisioned public-key
+ *  details      Call this function to encrypt message of any size
+ *               An ephemeral X25519 key agrees a secret with the public-key, HKDF-SHA256 derives the data key
+ *               and the message is encrypted with the instance AEAD, output is a self-describing envelope
+ *  \param[in]   message: message to encrypt
+ *  \param[out]  -
+ *  \precondition: Call init before using this function
+ *  \reentrant:  FALSE
+ *  \return      Encrypted array
+ *               Error code if any
+ ****************************************************************************************************************/
+fn encrypt(&self, message: &[u8]) -> Result<Vec<u8>, E2eError> {
+    let isinit = self.isinit.lock().unwrap();
+    if !*isinit {
+        return Err(E2eError::NotInitialized);
+    }
+    let pub_key = self.pubkey.as_ref().ok_or(E2eError::NotInitialized)?;
+    let pub_key = pub_key.lock().unwrap();
+
+    let ephemeral_key = EphemeralSecret::random_from_rng(rand::thread_rng());
+    let ephemeral_pub_key = PublicKey::from(&ephemeral_key);
+    let shared_secret = ephemeral_key.diffie_hellman(&pub_key);
+    if !shared_secret.was_contributory() {
+        return Err(E2eError::MalformedKey("X25519 public key is a low order point".to_string()));
+    }
+    let data_key = derive_data_key(shared_secret.as_bytes(), &ephemeral_pub_key, &pub_key)?;
+    envelope::seal(self.aead.envelope_scheme(), ephemeral_pub_key.as_bytes(), &data_key, message)
+}
+
+/*****************************************************************************************************************
+ *  e2esdk::decrypt function
+ *  brief        Decrypt message with provisioned private-key
+ *  details      Call this function to decrypt message
+ *               The AEAD comes from the envelope, so messages of both AEAD can be decrypted
+ *  \param[in]   message: message to decrypt
+ *  \param[out]  -
+ *  \precondition: Call init before using this function
+ *  \reentrant:  FALSE
+ *  \return      Decrypted array
+ *               Error code if any
+ ****************************************************************************************************************/
+fn decrypt(&self, encrypted_message: &[u8]) -> Result<Vec<u8>, E2eError> {
+    let isinit = self.isinit.lock().unwrap();
+    if !*isinit {
+        return Err(E2eError::NotInitialized);
+    }
+    let priv_key = self.privkey.as_ref().ok_or(E2eError::MissingPrivateKey)?;
+    let priv_key = priv_key.lock().unwrap();
+
+    let envelope = envelope::parse(encrypted_message)?;
+    if AeadAlgorithm::from_envelope_scheme(envelope.scheme).is_none() {
+        return Err(E2eError::Unsupported(format!("envelope scheme {}", envelope.scheme)));
+    }
+    let ephemeral_pub_key: [u8; X25519_KEY_LEN] = envelope.wrapped_key.try_into()
+        .map_err(|_| E2eError::InvalidFormat("wrong ephemeral key length".to_string()))?;
+    let ephemeral_pub_key = PublicKey::from(ephemeral_pub_key);
+
+    let shared_secret = priv_key.diffie_hellman(&ephemeral_pub_key);
+    if !shared_secret.was_contributory() {
+        return Err(E2eError::InvalidFormat("X25519 ephemeral key is a low order point".to_string()));
+    }
+    let data_key = derive_data_key(shared_secret.as_bytes(), &ephemeral_pub_key, &PublicKey::from(&*priv_key))?;
+    envelope::open(&envelope, &data_key)
+}
+
+/*****************************************************************************************************************
+ *  e2esdk::encrypted_size function
+ *  brief        Get the exact size of the output of encrypt for a message of message_len bytes
+ *  details      The envelope carries a 32-byte ephemeral public key, the size does not depend on the key pair
+ *  \param[in]   message_len: size of the message to encrypt
+ *  \param[out]  -
+ *  \precondition: -
+ *  \reentrant:  TRUE
+ *  \return      Encrypted size
+ *               Error code if any
+ ****************************************************************************************************************/
+fn encrypted_size(&self, message_len: usize) -> Result<usize, E2eError> {
+    envelope::sealed_len(X25519_KEY_LEN, message_len)
+}
+}
+/*****************************************************************************************************************
+ * End trait E2eX25519 implementation for E2eCyber
+ *****************************************************************************************************************/
+
+/*****************************************************************************************************************
+ * Start implementation for E2eX25519 object
+ *****************************************************************************************************************/
+impl E2eX25519 {
+pub fn new() -> Self {
+    E2eX25519 {
+        isinit: Arc::new(Mutex::new(false)),
+        privkey: None,
+        pubkey: None,
+        aead: AeadAlgorithm::default(),
+    }
+}
+
+pub fn with_aead(aead: AeadAlgorithm) -> Self {
+    let mut instance = E2eX25519::new();
+    instance.aead = aead;
+    instance
+}
+
+pub fn is_initialized(&self) -> bool {
+    *self.isinit.lock().unwrap()
+}
+
+// False before init and after init_public
+pub fn has_private_key(&self) -> bool {
+    self.privkey.is_some()
+}
+
+// AEAD used by encrypt, decrypt always follows the AEAD recorded in the envelope
+pub fn set_aead(&mut self, aead: AeadAlgorithm) {
+    self.aead = aead;
+}
+
+pub fn aead(&self) -> AeadAlgorithm {
+    self.aead
+}
+
+fn set_keys(&mut self, priv_key: Option<StaticSecret>, pub_key: PublicKey) {
+    self.privkey = priv_key.map(|priv_key| Arc::new(Mutex::new(priv_key)));
+    self.pubkey = Some(Arc::new(Mutex::new(pub_key)));
+    *self.isinit.lock().unwrap() = true;
+}
+}
+
+impl Default for E2eX25519 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+/*****************************************************************************************************************
+ * End implementation for E2eX25519 object
+ *****************************************************************************************************************/
+
+/*****************************************************************************************************************
+ * Start helpers for X25519 keys and key derivation
+ *****************************************************************************************************************/
+impl AeadAlgorithm {
+    pub(crate) fn envelope_scheme(self) -> u8 {
+        match self {
+            AeadAlgorithm::ChaCha20Poly1305 => envelope::SCHEME_X25519_HKDF_SHA256_CHACHA20POLY1305,
+            AeadAlgorithm::Aes256Gcm => envelope::SCHEME_X25519_HKDF_SHA256_AES256GCM,
+        }
+    }
+
+    pub(crate) fn from_envelope_scheme(scheme: u8) -> Option<Self> {
+        match scheme {
+            envelope::SCHEME_X25519_HKDF_SHA256_CHACHA20POLY1305 => Some(AeadAlgorithm::ChaCha20Poly1305),
+            envelope::SCHEME_X25519_HKDF_SHA256_AES256GCM => Some(AeadAlgorithm::Aes256Gcm),
+            _ => None,
+        }
+    }
+}
+
+// Both public keys are used as salt so the data key is bound to this sender/recipient exchange
+fn derive_data_key(shared_secret: &[u8], ephemeral_pub_key: &PublicKey, recipient_pub_key: &PublicKey) -> Result<[u8; envelope::DATA_KEY_LEN], E2eError> {
+    let mut salt = [0u8; 2 * X25519_KEY_LEN];
+    salt[..X25519_KEY_LEN].copy_from_slice(ephemeral_pub_key.as_bytes());
+    salt[X25519_KEY_LEN..].copy_from_slice(recipient_pub_key.as_bytes());
+
+    let mut data_key = [0u8; envelope::DATA_KEY_LEN];
+    Hkdf::<Sha256>::new(Some(&salt), shared_secret)
+        .expand(HKDF_INFO, &mut data_key)
+        .map_err(|_| E2eError::Crypto("HKDF expand failed".to_string()))?;
+    Ok(data_key)
+}
+
+fn key_error(err: impl std::fmt::Display) -> E2eError {
+    E2eError::MalformedKey(format!("X25519 key: {}", err))
+}
+
+fn check_key_format(format: KeyFormat) -> Result<(), E2eError> {
+    match format {
+        KeyFormat::Pkcs8 => Ok(()),
+        KeyFormat::Pkcs1 => Err(E2eError::Unsupported("PKCS#1 for X25519 keys".to_string())),
+    }
+}
+
+fn private_key_document(priv_key: &StaticSecret) -> Result<SecretDocument, E2eError> {
+    // PKCS#8 privateKey is an OCTET STRING wrapping the raw key (RFC 8410 CurvePrivateKey)
+    let priv_bytes = priv_key.to_bytes();
+    let curve_priv_key = OctetStringRef::new(&priv_bytes).map_err(key_error)?.to_der().map_err(key_error)?;
+    let priv_info = PrivateKeyInfo::new(AlgorithmIdentifierRef { oid: X25519_OID, parameters: None }, &curve_priv_key);
+    SecretDocument::encode_msg(&priv_info).map_err(key_error)
+}
+
+fn public_key_document(pub_key: &PublicKey) -> Result<Document, E2eError> {
+    let pub_info = SubjectPublicKeyInfoRef {
+        algorithm: AlgorithmIdentifierRef { oid: X25519_OID, parameters: None },
+        subject_public_key: BitStringRef::from_bytes(pub_key.as_bytes()).map_err(key_error)?,
+    };
+    Document::encode_msg(&pub_info).map_err(key_error)
+}
+
+fn encode_private_key_pem(priv_key: &StaticSecret) -> Result<String, E2eError> {
+    let priv_pem = private_key_document(priv_key)?
+        .to_pem(PrivateKeyInfo::PEM_LABEL, LineEnding::LF).map_err(key_error)?;
+    Ok(priv_pem.to_string())
+}
+
+fn encode_public_key_pem(pub_key: &PublicKey) -> Result<String, E2eError> {
+    public_key_document(pub_key)?
+        .to_pem(SubjectPublicKeyInfoRef::PEM_LABEL, LineEnding::LF).map_err(key_error)
+}
+
+// PKCS#8 in PEM or DER
+fn decode_private_key(priv_key: &[u8]) -> Result<StaticSecret, E2eError> {
+    let Some(priv_pem) = pem_str(priv_key) else {
+        return decode_private_key_der(priv_key);
+    };
+    let (label, document) = SecretDocument::from_pem(priv_pem).map_err(key_error)?;
+    if label != PrivateKeyInfo::PEM_LABEL {
+        return Err(key_error(format!("unexpected PEM label {}", label)));
+    }
+    decode_private_key_der(document.as_bytes())
+}
+
+fn decode_private_key_der(priv_der: &[u8]) -> Result<StaticSecret, E2eError> {
+    let priv_info = PrivateKeyInfo::from_der(priv_der).map_err(key_error)?;
+    if priv_info.algorithm.oid != X25519_OID {
+        return Err(key_error("not an X25519 private key"));
+    }
+    let curve_priv_key = OctetStringRef::from_der(priv_info.private_key).map_err(key_error)?;
+    let priv_bytes: [u8; X25519_KEY_LEN] = curve_priv_key.as_bytes().try_into()
+        .map_err(|_| key_error("wrong private key length"))?;
+    Ok(StaticSecret::from(priv_bytes))
+}
+
+// SPKI in PEM or DER
+fn decode_public_key(pub_key: &[u8]) -> Result<PublicKey, E2eError> {
+    let Some(pub_pem) = pem_str(pub_key) else {
+        return decode_public_key_der(pub_key);
+    };
+    let (label, document) = Document::from_pem(pub_pem).map_err(key_error)?;
+    if label != SubjectPublicKeyInfoRef::PEM_LABEL {
+        return Err(key_error(format!("unexpected PEM label {}", label)));
+    }
+    decode_public_key_der(document.as_bytes())
+}
+
+fn decode_public_key_der(pub_der: &[u8]) -> Result<PublicKey, E2eError> {
+    let pub_info = SubjectPublicKeyInfoRef::from_der(pub_der).map_err(key_error)?;
+    if pub_info.algorithm.oid != X25519_OID {
+        return Err(key_error("not an X25519 public key"));
+    }
+    let pub_bytes: [u8; X25519_KEY_LEN] = pub_info.subject_public_key.as_bytes()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| key_error("wrong public key length"))?;
+    Ok(PublicKey::from(pub_bytes))
+}
+/*****************************************************************************************************************
+ * End helpers for X25519 keys and key derivation
+ *****************************************************************************************************************/
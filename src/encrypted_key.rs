@@ -0,0 +1,91 @@
+use crate::e2e_implementation::{pem_str, KeyEncoding};
+use crate::error::E2eError;
+
+use pkcs8::der::pem::PemLabel;
+use pkcs8::der::{Document, SecretDocument};
+use pkcs8::pkcs5::{self, pbes2, scrypt};
+use pkcs8::{EncryptedPrivateKeyInfo, LineEnding, PrivateKeyInfo};
+use rand::RngCore;
+
+/*****************************************************************************************************************
+ * Standard password protected private keys, PKCS#8 EncryptedPrivateKeyInfo ("ENCRYPTED PRIVATE KEY" in PEM)
+ * protected with PBES2 and AES-256-CBC, as written by `openssl genpkey -aes-256-cbc` or `openssl pkcs8 -topk8`.
+ * This is an alternative to the exsecure container for keys which are shared with other tools.
+ *****************************************************************************************************************/
+const SALT_LEN: usize = 16;
+const IV_LEN: usize = 16;
+// Same cost as OpenSSL, which refuses scrypt parameters needing more than 32 MiB
+const SCRYPT_LOG_N: u8 = 14;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+const AES256_KEY_LEN: usize = 32;
+// OWASP recommended PBKDF2-HMAC-SHA256 iterations, same as KdfParams::pbkdf2_default
+const PBKDF2_ITERATIONS: u32 = 600_000;
+
+// Password based key derivation of PBES2, decryption accepts both
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Pkcs8Kdf {
+    #[default]
+    Scrypt,
+    // Older tools may only read PBKDF2
+    Pbkdf2Sha256,
+}
+
+// Encrypt a PKCS#8 PrivateKeyInfo in DER
+pub(crate) fn encrypt_private_key(priv_der: &[u8], password: &str, kdf: Pkcs8Kdf, encoding: KeyEncoding) -> Result<Vec<u8>, E2eError> {
+    let mut salt = [0u8; SALT_LEN];
+    let mut iv = [0u8; IV_LEN];
+    rand::thread_rng().fill_bytes(&mut salt);
+    rand::thread_rng().fill_bytes(&mut iv);
+
+    let params = match kdf {
+        Pkcs8Kdf::Scrypt => {
+            let scrypt_params = scrypt::Params::new(SCRYPT_LOG_N, SCRYPT_R, SCRYPT_P, AES256_KEY_LEN)
+                .map_err(|err| E2eError::Crypto(err.to_string()))?;
+            pbes2::Parameters::scrypt_aes256cbc(scrypt_params, &salt, &iv)
+        }
+        Pkcs8Kdf::Pbkdf2Sha256 => pbes2::Parameters::pbkdf2_sha256_aes256cbc(PBKDF2_ITERATIONS, &salt, &iv),
+    }
+    .map_err(crypto_error)?;
+
+    let priv_info = PrivateKeyInfo::try_from(priv_der).map_err(malformed)?;
+    let encrypted = priv_info.encrypt_with_params(params, password).map_err(crypto_error)?;
+    match encoding {
+        KeyEncoding::Pem => encrypted.to_pem(EncryptedPrivateKeyInfo::PEM_LABEL, LineEnding::LF)
+            .map(|pem| pem.as_bytes().to_vec())
+            .map_err(crypto_error),
+        KeyEncoding::Der => Ok(encrypted.as_bytes().to_vec()),
+    }
+}
+
+// Decrypt an EncryptedPrivateKeyInfo in PEM or DER, the result is a PKCS#8 PrivateKeyInfo in DER
+pub(crate) fn decrypt_private_key(priv_key: &[u8], password: &str) -> Result<SecretDocument, E2eError> {
+    let document;
+    let encrypted_der = match pem_str(priv_key) {
+        Some(priv_pem) => {
+            let (label, pem_document) = Document::from_pem(priv_pem).map_err(malformed)?;
+            if label != EncryptedPrivateKeyInfo::PEM_LABEL {
+                return Err(malformed(format!("unexpected PEM label {}", label)));
+            }
+            document = pem_document;
+            document.as_bytes()
+        }
+        None => priv_key,
+    };
+    let encrypted = EncryptedPrivateKeyInfo::try_from(encrypted_der).map_err(malformed)?;
+    encrypted.decrypt(password).map_err(|err| match err {
+        // PBES2 has no authentication, a wrong password fails the CBC padding check (reported as EncryptFailed
+        // by pkcs5) or, when the padding happens to be valid, decrypts to something which is not DER
+        pkcs8::Error::EncryptedPrivateKey(pkcs5::Error::DecryptFailed | pkcs5::Error::EncryptFailed)
+        | pkcs8::Error::Asn1(_) => E2eError::WrongPassword,
+        err => E2eError::Unsupported(format!("encrypted private key: {}", err)),
+    })
+}
+
+fn malformed(err: impl std::fmt::Display) -> E2eError {
+    E2eError::MalformedKey(format!("encrypted private key: {}", err))
+}
+
+fn crypto_error(err: impl std::fmt::Display) -> E2eError {
+    E2eError::Crypto(err.to_string())
+}
@@ -0,0 +1,226 @@
+use crate::error::E2eError;
+
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use chacha20poly1305::ChaCha20Poly1305;
+use rand::RngCore;
+
+/*****************************************************************************************************************
+ * Hybrid envelope layout (all integers big-endian)
+ *   magic "E2EE" (4) | version (1) | scheme (1) | wrapped key length (2) | wrapped key | nonce (12) | ciphertext+tag
+ * The whole header (everything before the ciphertext) is bound to the AEAD tag as associated data.
+ * For X25519 schemes the "wrapped key" is the ephemeral public key, the data key is derived with HKDF.
+ *
+ * Multi-recipient envelope, version 2, the same data key is wrapped once per recipient
+ *   magic "E2EE" (4) | version 2 (1) | scheme (1) | slot count (2) | slots | nonce (12) | ciphertext+tag
+ *   slot: recipient fingerprint (32) | wrap scheme (1) | wrapped key length (2) | wrapped key
+ * The fingerprint is SHA-256 of the recipient public key in SPKI DER, the wrap scheme is one of the RSA schemes.
+ *****************************************************************************************************************/
+const MAGIC: &[u8; 4] = b"E2EE";
+const VERSION: u8 = 1;
+const MULTI_VERSION: u8 = 2;
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+const FIXED_HEADER_LEN: usize = 4 + 1 + 1 + 2;
+
+pub(crate) const DATA_KEY_LEN: usize = 32;
+pub(crate) const FINGERPRINT_LEN: usize = 32;
+
+// RSA PKCS#1 v1.5 wrapped AES-256-GCM data key
+pub(crate) const SCHEME_RSA_PKCS1V15_AES256GCM: u8 = 1;
+// RSA-OAEP (SHA-256) wrapped AES-256-GCM data key
+pub(crate) const SCHEME_RSA_OAEP_SHA256_AES256GCM: u8 = 2;
+// X25519 ECDH + HKDF-SHA256 derived ChaCha20-Poly1305 data key
+pub(crate) const SCHEME_X25519_HKDF_SHA256_CHACHA20POLY1305: u8 = 3;
+// X25519 ECDH + HKDF-SHA256 derived AES-256-GCM data key
+pub(crate) const SCHEME_X25519_HKDF_SHA256_AES256GCM: u8 = 4;
+// AES-256-GCM data key wrapped per recipient, multi-recipient envelope only
+pub(crate) const SCHEME_MULTI_AES256GCM: u8 = 5;
+
+pub(crate) struct Envelope<'a> {
+    pub(crate) scheme: u8,
+    pub(crate) wrapped_key: &'a [u8],
+    header: &'a [u8],
+    nonce: &'a [u8],
+    ciphertext: &'a [u8],
+}
+
+// Data key wrapped for one recipient of a multi-recipient envelope
+pub(crate) struct RecipientSlot<'a> {
+    pub(crate) fingerprint: &'a [u8],
+    pub(crate) scheme: u8,
+    pub(crate) wrapped_key: &'a [u8],
+}
+
+pub(crate) struct MultiEnvelope<'a> {
+    pub(crate) slots: Vec<RecipientSlot<'a>>,
+    // Payload part, opened with open like a single recipient envelope
+    pub(crate) payload: Envelope<'a>,
+}
+
+// Generate a fresh random data key for one message
+pub(crate) fn generate_data_key() -> [u8; DATA_KEY_LEN] {
+    let mut key = [0u8; DATA_KEY_LEN];
+    rand::thread_rng().fill_bytes(&mut key);
+    key
+}
+
+// Check whether data starts with the envelope magic
+pub(crate) fn is_envelope(data: &[u8]) -> bool {
+    data.len() >= FIXED_HEADER_LEN && &data[0..4] == MAGIC
+}
+
+// Check whether data is a multi-recipient envelope
+pub(crate) fn is_multi_envelope(data: &[u8]) -> bool {
+    is_envelope(data) && data[4] == MULTI_VERSION
+}
+
+fn wrapped_key_len(wrapped_key: &[u8]) -> Result<[u8; 2], E2eError> {
+    u16::try_from(wrapped_key.len())
+        .map(u16::to_be_bytes)
+        .map_err(|_| E2eError::InvalidInput("wrapped key too long".to_string()))
+}
+
+// Encrypt message with data_key and pack it together with the wrapped data key
+pub(crate) fn seal(scheme: u8, wrapped_key: &[u8], data_key: &[u8], message: &[u8]) -> Result<Vec<u8>, E2eError> {
+    let mut envelope = Vec::with_capacity(sealed_len(wrapped_key.len(), message.len())?);
+    envelope.extend_from_slice(MAGIC);
+    envelope.push(VERSION);
+    envelope.push(scheme);
+    envelope.extend_from_slice(&wrapped_key_len(wrapped_key)?);
+    envelope.extend_from_slice(wrapped_key);
+    seal_payload(envelope, scheme, data_key, message)
+}
+
+// Encrypt message once with data_key, every slot carries data_key wrapped for one recipient
+pub(crate) fn seal_multi(slots: &[RecipientSlot], data_key: &[u8], message: &[u8]) -> Result<Vec<u8>, E2eError> {
+    if slots.is_empty() {
+        return Err(E2eError::InvalidInput("no recipient".to_string()));
+    }
+    let slot_count = u16::try_from(slots.len())
+        .map_err(|_| E2eError::InvalidInput("too many recipients".to_string()))?;
+
+    let mut envelope = Vec::new();
+    envelope.extend_from_slice(MAGIC);
+    envelope.push(MULTI_VERSION);
+    envelope.push(SCHEME_MULTI_AES256GCM);
+    envelope.extend_from_slice(&slot_count.to_be_bytes());
+    for slot in slots {
+        envelope.extend_from_slice(slot.fingerprint);
+        envelope.push(slot.scheme);
+        envelope.extend_from_slice(&wrapped_key_len(slot.wrapped_key)?);
+        envelope.extend_from_slice(slot.wrapped_key);
+    }
+    seal_payload(envelope, SCHEME_MULTI_AES256GCM, data_key, message)
+}
+
+// Append nonce and ciphertext to header, the header is the associated data
+fn seal_payload(mut envelope: Vec<u8>, scheme: u8, data_key: &[u8], message: &[u8]) -> Result<Vec<u8>, E2eError> {
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    envelope.extend_from_slice(&nonce);
+
+    let payload = Payload { msg: message, aad: &envelope };
+    let ciphertext = if scheme == SCHEME_X25519_HKDF_SHA256_CHACHA20POLY1305 {
+        ChaCha20Poly1305::new_from_slice(data_key)
+            .map_err(|_| E2eError::Crypto("wrong data key length".to_string()))?
+            .encrypt(Nonce::from_slice(&nonce), payload)
+    } else {
+        Aes256Gcm::new_from_slice(data_key)
+            .map_err(|_| E2eError::Crypto("wrong data key length".to_string()))?
+            .encrypt(Nonce::from_slice(&nonce), payload)
+    }
+    // The only failure of AEAD encryption is a message over the cipher limit
+    .map_err(|_| E2eError::MessageTooLong)?;
+    envelope.extend_from_slice(&ciphertext);
+
+    Ok(envelope)
+}
+
+// Size of the envelope sealing a message of message_len bytes
+pub(crate) fn sealed_len(wrapped_key_len: usize, message_len: usize) -> Result<usize, E2eError> {
+    message_len
+        .checked_add(FIXED_HEADER_LEN + wrapped_key_len + NONCE_LEN + TAG_LEN)
+        .ok_or(E2eError::MessageTooLong)
+}
+
+// Split an envelope into its parts, no decryption is done here
+pub(crate) fn parse(data: &[u8]) -> Result<Envelope<'_>, E2eError> {
+    if !is_envelope(data) {
+        return Err(E2eError::InvalidFormat("not an E2E envelope".to_string()));
+    }
+    if data[4] != VERSION {
+        return Err(E2eError::Unsupported(format!("envelope version {}", data[4])));
+    }
+    let scheme = data[5];
+    let wrapped_key_len = u16::from_be_bytes([data[6], data[7]]) as usize;
+    let header_len = FIXED_HEADER_LEN + wrapped_key_len + NONCE_LEN;
+    if data.len() < header_len {
+        return Err(E2eError::InvalidFormat("envelope too short".to_string()));
+    }
+
+    Ok(Envelope {
+        scheme,
+        wrapped_key: &data[FIXED_HEADER_LEN..FIXED_HEADER_LEN + wrapped_key_len],
+        header: &data[..header_len],
+        nonce: &data[header_len - NONCE_LEN..header_len],
+        ciphertext: &data[header_len..],
+    })
+}
+
+// Split a multi-recipient envelope into its slots and payload, no decryption is done here
+pub(crate) fn parse_multi(data: &[u8]) -> Result<MultiEnvelope<'_>, E2eError> {
+    if !is_multi_envelope(data) {
+        return Err(E2eError::InvalidFormat("not a multi-recipient E2E envelope".to_string()));
+    }
+    let scheme = data[5];
+    if scheme != SCHEME_MULTI_AES256GCM {
+        return Err(E2eError::Unsupported(format!("envelope scheme {}", scheme)));
+    }
+    let too_short = || E2eError::InvalidFormat("envelope too short".to_string());
+    let slot_count = u16::from_be_bytes([data[6], data[7]]) as usize;
+
+    let mut slots = Vec::with_capacity(slot_count);
+    let mut offset = FIXED_HEADER_LEN;
+    for _ in 0..slot_count {
+        let slot_header = data.get(offset..offset + FINGERPRINT_LEN + 3).ok_or_else(too_short)?;
+        let wrapped_key_len = u16::from_be_bytes([slot_header[FINGERPRINT_LEN + 1], slot_header[FINGERPRINT_LEN + 2]]) as usize;
+        let wrapped_key_start = offset + FINGERPRINT_LEN + 3;
+        slots.push(RecipientSlot {
+            fingerprint: &slot_header[..FINGERPRINT_LEN],
+            scheme: slot_header[FINGERPRINT_LEN],
+            wrapped_key: data.get(wrapped_key_start..wrapped_key_start + wrapped_key_len).ok_or_else(too_short)?,
+        });
+        offset = wrapped_key_start + wrapped_key_len;
+    }
+    let header_len = offset + NONCE_LEN;
+    if data.len() < header_len {
+        return Err(too_short());
+    }
+
+    Ok(MultiEnvelope {
+        slots,
+        payload: Envelope {
+            scheme,
+            wrapped_key: &[],
+            header: &data[..header_len],
+            nonce: &data[offset..header_len],
+            ciphertext: &data[header_len..],
+        },
+    })
+}
+
+// Decrypt the envelope payload with the unwrapped (or derived) data key
+pub(crate) fn open(envelope: &Envelope, data_key: &[u8]) -> Result<Vec<u8>, E2eError> {
+    let payload = Payload { msg: envelope.ciphertext, aad: envelope.header };
+    if envelope.scheme == SCHEME_X25519_HKDF_SHA256_CHACHA20POLY1305 {
+        ChaCha20Poly1305::new_from_slice(data_key)
+            .map_err(|_| E2eError::DecryptionFailed)?
+            .decrypt(Nonce::from_slice(envelope.nonce), payload)
+    } else {
+        Aes256Gcm::new_from_slice(data_key)
+            .map_err(|_| E2eError::DecryptionFailed)?
+            .decrypt(Nonce::from_slice(envelope.nonce), payload)
+    }
+    .map_err(|_| E2eError::DecryptionFailed)
+}
@@ -0,0 +1,111 @@
+use std::error::Error;
+use std::fmt;
+use std::io::{self, ErrorKind};
+
+// Error returned by every public function of the SDK
+// It converts into io::Error (and back), so code written against io::Result keeps compiling
+// Variants are added by new versions, matches outside the SDK need a wildcard arm
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum E2eError {
+    // Keys are not provisioned, call init first
+    NotInitialized,
+    // Instance was initialized with init_public, decrypt and sign need the private key
+    MissingPrivateKey,
+    // Password protected data does not authenticate: wrong password, corrupted or truncated data
+    WrongPassword,
+    // Message does not authenticate or its key cannot be unwrapped: wrong key pair, corrupted or tampered message
+    DecryptionFailed,
+    // Key is not a valid PEM/DER key of the expected type
+    MalformedKey(String),
+    // Private key and public key do not belong to the same pair
+    KeyPairMismatch,
+    // Message is bigger than what the cipher can encrypt
+    MessageTooLong,
+    // Signature does not match the message
+    InvalidSignature,
+    // Encrypted data is not a valid envelope or container
+    InvalidFormat(String),
+    // Key size, format version, cipher or KDF not supported by this version
+    Unsupported(String),
+    // Argument given by the caller is not valid
+    InvalidInput(String),
+    // Output buffer given by the caller cannot hold the result
+    BufferTooSmall { required: usize },
+    // Unexpected failure of the crypto backend
+    Crypto(String),
+    Io(io::Error),
+}
+
+impl E2eError {
+    // Closest io::ErrorKind, also the kind of the io::Error this error converts into
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            E2eError::NotInitialized => ErrorKind::Other,
+            E2eError::MissingPrivateKey => ErrorKind::Other,
+            E2eError::WrongPassword => ErrorKind::InvalidData,
+            E2eError::DecryptionFailed => ErrorKind::InvalidData,
+            E2eError::MalformedKey(_) => ErrorKind::InvalidData,
+            E2eError::KeyPairMismatch => ErrorKind::InvalidData,
+            E2eError::MessageTooLong => ErrorKind::InvalidInput,
+            E2eError::InvalidSignature => ErrorKind::InvalidData,
+            E2eError::InvalidFormat(_) => ErrorKind::InvalidData,
+            E2eError::Unsupported(_) => ErrorKind::Unsupported,
+            E2eError::InvalidInput(_) => ErrorKind::InvalidInput,
+            E2eError::BufferTooSmall { .. } => ErrorKind::InvalidInput,
+            E2eError::Crypto(_) => ErrorKind::Other,
+            E2eError::Io(err) => err.kind(),
+        }
+    }
+}
+
+impl fmt::Display for E2eError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            E2eError::NotInitialized => write!(f, "not initialized, call init first"),
+            E2eError::MissingPrivateKey => write!(f, "private key required, initialized with a public key only"),
+            E2eError::WrongPassword => write!(f, "authentication failed: wrong password or corrupted data"),
+            E2eError::DecryptionFailed => write!(f, "decryption failed: wrong key or corrupted message"),
+            E2eError::MalformedKey(detail) => write!(f, "malformed key: {}", detail),
+            E2eError::KeyPairMismatch => write!(f, "key pair verification failed"),
+            E2eError::MessageTooLong => write!(f, "message too long"),
+            E2eError::InvalidSignature => write!(f, "signature verification failed"),
+            E2eError::InvalidFormat(detail) => write!(f, "invalid format: {}", detail),
+            E2eError::Unsupported(detail) => write!(f, "unsupported {}", detail),
+            E2eError::InvalidInput(detail) => write!(f, "invalid input: {}", detail),
+            E2eError::BufferTooSmall { required } => write!(f, "buffer too small, {} bytes required", required),
+            E2eError::Crypto(detail) => write!(f, "crypto error: {}", detail),
+            E2eError::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for E2eError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            E2eError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+// An io::Error carrying an E2eError (e.g. returned through Read/Write adapters) gives the original error back
+impl From<io::Error> for E2eError {
+    fn from(err: io::Error) -> Self {
+        match err.get_ref() {
+            Some(inner) if inner.is::<E2eError>() => {
+                *err.into_inner().and_then(|inner| inner.downcast().ok()).expect("inner error checked above")
+            }
+            _ => E2eError::Io(err),
+        }
+    }
+}
+
+impl From<E2eError> for io::Error {
+    fn from(err: E2eError) -> Self {
+        match err {
+            E2eError::Io(err) => err,
+            err => io::Error::new(err.kind(), err),
+        }
+    }
+}
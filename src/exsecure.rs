@@ -1,110 +1,508 @@
-use std::io::{self, Error, ErrorKind, Read, Write};
+use std::io::{self, BufReader, BufWriter, ErrorKind, Read, Write};
 use std::fs::{self, File};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
+use aes_gcm::aead::stream::{DecryptorBE32, EncryptorBE32};
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use argon2::{Algorithm, Argon2, Params, Version};
 use ctr::cipher::{KeyIvInit, StreamCipher};
 use aes::cipher::generic_array::GenericArray;
-use sha2::{Sha256, Digest};
-use rand::Rng;
+use glob::Pattern;
+use log::warn;
+use sha2::{Digest, Sha256};
+use rand::RngCore;
+use rsa::pkcs1;
+use pkcs8::spki::SubjectPublicKeyInfoRef;
+use crate::e2e_implementation::pem_str;
+use crate::error::E2eError;
 
 type Aes128Ctr64LE = ctr::Ctr64LE<aes::Aes128>;
 
-const IV: &[u8] = b"tranngochung1996"; // Initialization vector - 16 bytes
+/*****************************************************************************************************************
+ * Container format of an encrypted file, version 1 (integers are big-endian):
+ *   magic "E2ES" (4) | format version (1) | cipher id (1) | kdf id (1) | kdf params | salt (16) | cipher fields
+ *   | encrypted data
+ * kdf id 1: Argon2id, params m_cost (KiB) | t_cost | p_cost as u32
+ * kdf id 2: PBKDF2-HMAC-SHA256, params iterations as u32
+ * cipher id 1: AES-256-GCM over the whole file
+ *   cipher fields: nonce (12), encrypted data: ciphertext | tag (16)
+ * cipher id 2: AES-256-GCM STREAM (big-endian 32-bit counter and last-segment flag in the nonce), written by this
+ *   version so files of any size are processed with constant memory
+ *   cipher fields: chunk size (4) | nonce prefix (7), encrypted data: segments of chunk size plaintext + tag (16),
+ *   the last one can be shorter and is always present, even for empty input
+ * The whole header (magic to cipher fields) is bound to every tag as associated data. Segment counter and last flag
+ * make reordered, dropped or truncated segments fail authentication.
+ * Since salt and cost are read back from the file, files protected with older costs still decrypt.
+ *
+ * Files without the magic are legacy files of SDK before the container: AES-128-CTR with a constant IV,
+ * the key is the first 16 bytes of SHA-256(password) and 4 random bytes are appended to the plaintext.
+ * They are still readable but carry no integrity check, use migrate_legacy_file to convert them.
+ *****************************************************************************************************************/
+const MAGIC: &[u8; 4] = b"E2ES";
+const FORMAT_VERSION: u8 = 1;
+const CIPHER_AES256GCM: u8 = 1;
+const CIPHER_AES256GCM_STREAM: u8 = 2;
+const KDF_ARGON2ID: u8 = 1;
+const KDF_PBKDF2_SHA256: u8 = 2;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const KEY_LEN: usize = 32;
+const TAG_LEN: usize = 16;
+// Nonce size of AES-GCM minus the 5 bytes of STREAM counter and last flag
+const STREAM_NONCE_PREFIX_LEN: usize = 7;
+const STREAM_CHUNK_SIZE: u32 = 64 * 1024;
+const LEGACY_IV: &[u8] = b"tranngochung1996";
+const LEGACY_PADDING_LEN: usize = 4;
+// Same limit as Linux when resolving a path
+const MAX_LINK_HOPS: usize = 40;
+
+// Upper bounds accepted when reading a header, so a crafted file cannot make us allocate or loop forever
+// The header is read before anything authenticates it, so m_cost stays close to the default (19 MiB)
+const MAX_ARGON2_M_COST: u32 = 256 * 1024; // 256 MiB, in KiB
+const MAX_ARGON2_T_COST: u32 = 64;
+const MAX_ARGON2_P_COST: u32 = 16;
+const MIN_PBKDF2_ITERATIONS: u32 = 1000;
+const MAX_PBKDF2_ITERATIONS: u32 = 100_000_000;
+const MAX_STREAM_CHUNK_SIZE: u32 = 16 * 1024 * 1024;
+
+// Password based key derivation used to protect files, Argon2id is the default
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KdfParams {
+    // m_cost in KiB, t_cost iterations, p_cost lanes
+    Argon2id { m_cost: u32, t_cost: u32, p_cost: u32 },
+    Pbkdf2Sha256 { iterations: u32 },
+}
+
+impl KdfParams {
+    // OWASP recommended Argon2id costs (19 MiB, 2 iterations, 1 lane)
+    pub fn argon2id_default() -> Self {
+        KdfParams::Argon2id {
+            m_cost: Params::DEFAULT_M_COST,
+            t_cost: Params::DEFAULT_T_COST,
+            p_cost: Params::DEFAULT_P_COST,
+        }
+    }
+
+    // OWASP recommended PBKDF2-HMAC-SHA256 iterations, for platforms where Argon2 memory is a problem
+    pub fn pbkdf2_default() -> Self {
+        KdfParams::Pbkdf2Sha256 { iterations: 600_000 }
+    }
+
+    fn validate(&self) -> Result<(), E2eError> {
+        let valid = match *self {
+            KdfParams::Argon2id { m_cost, t_cost, p_cost } => {
+                m_cost <= MAX_ARGON2_M_COST && t_cost <= MAX_ARGON2_T_COST && p_cost <= MAX_ARGON2_P_COST
+                    && Params::new(m_cost, t_cost, p_cost, Some(KEY_LEN)).is_ok()
+            }
+            KdfParams::Pbkdf2Sha256 { iterations } => {
+                (MIN_PBKDF2_ITERATIONS..=MAX_PBKDF2_ITERATIONS).contains(&iterations)
+            }
+        };
+        if !valid {
+            return Err(E2eError::InvalidInput(format!("unsupported KDF parameters {:?}", self)));
+        }
+        Ok(())
+    }
+
+    fn write_header(&self, header: &mut Vec<u8>) {
+        match *self {
+            KdfParams::Argon2id { m_cost, t_cost, p_cost } => {
+                header.push(KDF_ARGON2ID);
+                header.extend_from_slice(&m_cost.to_be_bytes());
+                header.extend_from_slice(&t_cost.to_be_bytes());
+                header.extend_from_slice(&p_cost.to_be_bytes());
+            }
+            KdfParams::Pbkdf2Sha256 { iterations } => {
+                header.push(KDF_PBKDF2_SHA256);
+                header.extend_from_slice(&iterations.to_be_bytes());
+            }
+        }
+    }
+
+    // Size of kdf id and params in the header for kdf_id
+    fn header_len(kdf_id: u8) -> Result<usize, E2eError> {
+        match kdf_id {
+            KDF_ARGON2ID => Ok(13),
+            KDF_PBKDF2_SHA256 => Ok(5),
+            kdf => Err(E2eError::Unsupported(format!("KDF id {}", kdf))),
+        }
+    }
+
+    // Parse kdf id and params, return them with the number of bytes consumed
+    fn read_header(data: &[u8]) -> Result<(Self, usize), E2eError> {
+        let read_u32 = |offset: usize| -> Result<u32, E2eError> {
+            data.get(offset..offset + 4)
+                .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                .ok_or_else(|| E2eError::InvalidFormat("Data too short".to_string()))
+        };
+        let params = match data.first() {
+            Some(&KDF_ARGON2ID) => (KdfParams::Argon2id { m_cost: read_u32(1)?, t_cost: read_u32(5)?, p_cost: read_u32(9)? }, 13),
+            Some(&KDF_PBKDF2_SHA256) => (KdfParams::Pbkdf2Sha256 { iterations: read_u32(1)? }, 5),
+            Some(kdf) => return Err(E2eError::Unsupported(format!("KDF id {}", kdf))),
+            None => return Err(E2eError::InvalidFormat("Data too short".to_string())),
+        };
+        params.0.validate().map_err(|err| E2eError::InvalidFormat(err.to_string()))?;
+        Ok(params)
+    }
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        KdfParams::argon2id_default()
+    }
+}
+
+// Derive the 256-bit file key from password and salt
+fn derive_key(password: &str, params: &KdfParams, salt: &[u8]) -> Result<[u8; KEY_LEN], E2eError> {
+    let mut key = [0u8; KEY_LEN];
+    match *params {
+        KdfParams::Argon2id { m_cost, t_cost, p_cost } => {
+            let argon2_params = Params::new(m_cost, t_cost, p_cost, Some(KEY_LEN))
+                .map_err(|err| E2eError::InvalidInput(err.to_string()))?;
+            Argon2::new(Algorithm::Argon2id, Version::V0x13, argon2_params)
+                .hash_password_into(password.as_bytes(), salt, &mut key)
+                .map_err(|err| E2eError::Crypto(err.to_string()))?;
+        }
+        KdfParams::Pbkdf2Sha256 { iterations } => {
+            pbkdf2::pbkdf2_hmac::<Sha256>(password.as_bytes(), salt, iterations, &mut key);
+        }
+    }
+    Ok(key)
+}
 
-// Hash the password to create a 128-bit key
-fn hash_aes_key(password: &str) -> [u8; 16] {
-    let mut hasher = Sha256::new();
-    hasher.update(password.as_bytes());
-    let result = hasher.finalize();
-    let mut key = [0u8; 16];
-    key.copy_from_slice(&result[0..16]);
-    key
+// Function to decrypt data with AES-256 GCM mode, fail if the tag does not match
+fn decrypt_aes256_gcm(encrypted_data: &[u8], nonce_bytes: &[u8], key: &[u8], aad: &[u8]) -> Result<Vec<u8>, E2eError> {
+    if nonce_bytes.len() != NONCE_LEN {
+        return Err(E2eError::InvalidFormat("wrong nonce length".to_string()));
+    }
+    let cipher = Aes256Gcm::new_from_slice(key)
+        .map_err(|_| E2eError::Crypto("wrong key length".to_string()))?;
+
+    cipher.decrypt(Nonce::from_slice(nonce_bytes), Payload { msg: encrypted_data, aad })
+        .map_err(|_| E2eError::WrongPassword)
 }
 
-// Function to encrypt data with AES-128 CTR mode, adding 4 random bytes at the end
-fn encrypt_aes128_ctr(data_to_encrypt: &[u8], iv_bytes: &[u8], key: &[u8]) -> Result<Vec<u8>, io::Error> {
-    if iv_bytes.len() != 16 {
-        return Err(Error::new(ErrorKind::InvalidInput, "wrong IV length"));
+// Check the container magic, the rest of the header is validated by read_container_header
+fn is_container(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+// Read exactly buf.len() bytes, a short container is reported as invalid data
+fn read_header_field<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<(), E2eError> {
+    reader.read_exact(buf).map_err(|err| match err.kind() {
+        ErrorKind::UnexpectedEof => E2eError::InvalidFormat("Data too short".to_string()),
+        _ => E2eError::from(err),
+    })
+}
+
+// Read the container header, return the raw header (used as associated data), the cipher id and the file key
+fn read_container_header<R: Read>(reader: &mut R, password: &str) -> Result<(Vec<u8>, u8, [u8; KEY_LEN]), E2eError> {
+    let mut header = vec![0u8; MAGIC.len() + 3];
+    read_header_field(reader, &mut header)?;
+    if !is_container(&header) {
+        return Err(E2eError::InvalidFormat("not an encrypted container".to_string()));
     }
-    if key.len() != 16 {
-        return Err(Error::new(ErrorKind::InvalidInput, "wrong key length"));
+    let version = header[MAGIC.len()];
+    if version != FORMAT_VERSION {
+        return Err(E2eError::Unsupported(format!("container version {}", version)));
     }
+    let cipher = header[MAGIC.len() + 1];
+    let cipher_fields_len = match cipher {
+        CIPHER_AES256GCM => NONCE_LEN,
+        CIPHER_AES256GCM_STREAM => 4 + STREAM_NONCE_PREFIX_LEN,
+        _ => return Err(E2eError::Unsupported(format!("cipher id {}", cipher))),
+    };
+
+    let kdf_offset = MAGIC.len() + 2;
+    let salt_offset = kdf_offset + KdfParams::header_len(header[kdf_offset])?;
+    header.resize(salt_offset + SALT_LEN + cipher_fields_len, 0);
+    read_header_field(reader, &mut header[kdf_offset + 1..])?;
+
+    let (params, _) = KdfParams::read_header(&header[kdf_offset..])?;
+    let key = derive_key(password, &params, &header[salt_offset..salt_offset + SALT_LEN])?;
+    Ok((header, cipher, key))
+}
 
-    // Generate 4 random bytes
-    let mut rng = rand::thread_rng();
-    let mut random_bytes = [0u8; 4];
-    rng.fill(&mut random_bytes);
+// Build a new stream container header under fresh random salt and nonce prefix, return it with the file key
+fn new_stream_header(password: &str, params: &KdfParams) -> Result<(Vec<u8>, [u8; KEY_LEN]), E2eError> {
+    params.validate()?;
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce_prefix = [0u8; STREAM_NONCE_PREFIX_LEN];
+    rand::thread_rng().fill_bytes(&mut salt);
+    rand::thread_rng().fill_bytes(&mut nonce_prefix);
+    let key = derive_key(password, params, &salt)?;
 
-    // Append the random bytes to the end of the data
-    let mut data_with_random = Vec::from(data_to_encrypt);
-    data_with_random.extend_from_slice(&random_bytes);
+    let mut header = Vec::new();
+    header.extend_from_slice(MAGIC);
+    header.push(FORMAT_VERSION);
+    header.push(CIPHER_AES256GCM_STREAM);
+    params.write_header(&mut header);
+    header.extend_from_slice(&salt);
+    header.extend_from_slice(&STREAM_CHUNK_SIZE.to_be_bytes());
+    header.extend_from_slice(&nonce_prefix);
+    Ok((header, key))
+}
 
-    // Perform AES-128 CTR encryption
-    let mut encrypted_data = data_with_random.clone();
-    let mut cipher = Aes128Ctr64LE::new(GenericArray::from_slice(key), GenericArray::from_slice(iv_bytes));
+// Decrypt a legacy headerless file in place, there is no way to detect a wrong password here
+fn open_legacy_data(mut encrypted_data: Vec<u8>, password: &str) -> Result<Vec<u8>, E2eError> {
+    if encrypted_data.len() < LEGACY_PADDING_LEN {
+        return Err(E2eError::InvalidFormat("Data too short".to_string()));
+    }
+    let hash = Sha256::digest(password.as_bytes());
+    let mut cipher = Aes128Ctr64LE::new(GenericArray::from_slice(&hash[..16]), GenericArray::from_slice(LEGACY_IV));
     cipher.apply_keystream(&mut encrypted_data);
 
+    // Remove the random bytes appended by the legacy format
+    encrypted_data.truncate(encrypted_data.len() - LEGACY_PADDING_LEN);
     Ok(encrypted_data)
 }
 
-// Function to decrypt data with AES-128 CTR mode, removing the last 4 bytes
-fn decrypt_aes128_ctr(encrypted_data: &[u8], iv_bytes: &[u8], key: &[u8]) -> Result<Vec<u8>, io::Error> {
-    if iv_bytes.len() != 16 {
-        return Err(Error::new(ErrorKind::InvalidInput, "wrong IV length"));
+// Encrypt a file and overwrite it with encrypted data, the file is streamed so memory use does not grow with its size
+fn encrypt_file_and_save(file_path: &Path, password: &str, params: &KdfParams) -> Result<(), E2eError> {
+    let result = write_atomic(file_path, |encrypted_file| {
+        let mut source = BufReader::new(File::open(file_path)?);
+        let mut writer = EncryptWriter::new(BufWriter::new(encrypted_file), password, params)?;
+        io::copy(&mut source, &mut writer)?;
+        writer.finish()?;
+        Ok(())
+    });
+    if let Err(err) = &result {
+        warn!("Encryption error: {}: {}", file_path.display(), err);
     }
-    if key.len() != 16 {
-        return Err(Error::new(ErrorKind::InvalidInput, "wrong key length"));
+    result
+}
+
+// Encrypt one file of a folder unless it already carries the container
+fn encrypt_folder_entry(file_path: &Path, password: &str, params: &KdfParams) -> FileOutcome {
+    match is_encrypted_file(file_path) {
+        Ok(true) => FileOutcome::SkippedAlreadyEncrypted,
+        Ok(false) => match encrypt_file_and_save(file_path, password, params) {
+            Ok(()) => FileOutcome::Encrypted,
+            Err(err) => FileOutcome::Failed(err),
+        },
+        Err(err) => FileOutcome::Failed(err),
     }
+}
 
-    // Perform AES-128 CTR decryption
-    let mut decrypted_data = encrypted_data.to_vec();
-    let mut cipher = Aes128Ctr64LE::new(GenericArray::from_slice(key), GenericArray::from_slice(iv_bytes));
-    cipher.apply_keystream(&mut decrypted_data);
+// Decrypt one file of a folder, it is only overwritten once the whole content is authenticated
+fn decrypt_folder_entry(file_path: &Path, password: &str) -> FileOutcome {
+    // Headerless files are either plaintext or legacy files, which cannot be told apart without integrity check
+    match is_encrypted_file(file_path) {
+        Ok(true) => {}
+        Ok(false) => return FileOutcome::SkippedNotEncrypted,
+        Err(err) => return FileOutcome::Failed(err),
+    }
 
-    // Remove the last 4 bytes (which are the random bytes)
-    if decrypted_data.len() < 4 {
-        return Err(Error::new(ErrorKind::InvalidInput, "Data too short"));
+    let result = write_atomic(file_path, |decrypted_file| {
+        let mut reader = DecryptReader::new(BufReader::new(File::open(file_path)?), password)?;
+        io::copy(&mut reader, decrypted_file)?;
+        Ok(())
+    });
+    match result {
+        Ok(()) => FileOutcome::Decrypted,
+        Err(err) => {
+            warn!("Decryption error: {}: {}", file_path.display(), err);
+            FileOutcome::Failed(err)
+        }
     }
-    decrypted_data.truncate(decrypted_data.len() - 4);
+}
 
-    Ok(decrypted_data)
+// Convert one legacy file of a folder, files already carrying the container are skipped
+fn migrate_folder_entry(file_path: &Path, password: &str) -> FileOutcome {
+    match is_encrypted_file(file_path) {
+        Ok(true) => FileOutcome::SkippedAlreadyEncrypted,
+        Ok(false) => match migrate_legacy_file(file_path, password) {
+            Ok(()) => FileOutcome::Migrated,
+            Err(err) => {
+                warn!("Migration error: {}: {}", file_path.display(), err);
+                FileOutcome::Failed(err)
+            }
+        },
+        Err(err) => FileOutcome::Failed(err),
+    }
 }
 
-// Encrypt a file and overwrite it with encrypted data
-fn encrypt_file_and_save(file_path: &Path, key: &[u8]) -> io::Result<()> {
-    let mut file = File::open(file_path)?;
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer)?;
+fn compile_patterns(patterns: &[String]) -> Result<Vec<Pattern>, E2eError> {
+    patterns
+        .iter()
+        .map(|pattern| {
+            Pattern::new(pattern)
+                .map_err(|err| E2eError::InvalidInput(format!("pattern {:?}: {}", pattern, err)))
+        })
+        .collect()
+}
 
-    let ciphertext = match encrypt_aes128_ctr(&buffer, &IV, key) {
-        Ok(encrypted_data_record) => {encrypted_data_record}
-        Err(err) => {
-            eprintln!("Encryption error: {}", err);
-            return Err(err);
+// Walk a folder according to FolderOptions and collect what operation did with each selected file
+struct FolderWalker<'a, F> {
+    options: &'a FolderOptions,
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+    // Canonical paths of walked directories, so a followed link cannot make us loop
+    visited: Vec<PathBuf>,
+    operation: F,
+    report: FolderReport,
+}
+
+impl<'a, F> FolderWalker<'a, F>
+where
+    F: FnMut(&Path) -> FileOutcome,
+{
+fn is_excluded(&self, relative_path: &Path) -> bool {
+    self.exclude.iter().any(|pattern| pattern.matches_path(relative_path))
+}
+
+fn is_included(&self, relative_path: &Path) -> bool {
+    self.include.is_empty() || self.include.iter().any(|pattern| pattern.matches_path(relative_path))
+}
+
+fn can_descend(&self, depth: usize) -> bool {
+    self.options.recursive && self.options.max_depth.is_none_or(|max_depth| depth < max_depth)
+}
+
+fn fail(&mut self, path: PathBuf, err: E2eError) {
+    self.report.files.push(FileReport { path, outcome: FileOutcome::Failed(err) });
+}
+
+fn walk(&mut self, dir: &Path, relative_dir: &Path, depth: usize) -> Result<(), E2eError> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let relative_path = relative_dir.join(entry.file_name());
+
+        // Operation is applied to the link target, so the link itself is kept
+        let mut target = path.clone();
+        let file_type = match entry.file_type() {
+            Ok(file_type) => file_type,
+            Err(err) => {
+                self.fail(path, err.into());
+                continue;
+            }
+        };
+        if file_type.is_symlink() {
+            match self.options.symlinks {
+                SymlinkPolicy::Skip => continue,
+                SymlinkPolicy::Error => {
+                    self.fail(path, E2eError::InvalidInput("symbolic links are not allowed".to_string()));
+                    continue;
+                }
+                SymlinkPolicy::Follow => match fs::canonicalize(&path) {
+                    Ok(canonical_path) => target = canonical_path,
+                    Err(err) => {
+                        self.fail(path, err.into());
+                        continue;
+                    }
+                },
+            }
         }
-    };
 
-    let mut encrypted_file = File::create(file_path)?;
-    encrypted_file.write_all(&ciphertext)?;
+        let metadata = match fs::metadata(&target) {
+            Ok(metadata) => metadata,
+            Err(err) => {
+                self.fail(path, err.into());
+                continue;
+            }
+        };
+        if metadata.is_dir() {
+            if !self.can_descend(depth) || self.is_excluded(&relative_path) {
+                continue;
+            }
+            // Like any other entry, a directory removed or broken meanwhile only fails itself
+            let canonical_dir = match fs::canonicalize(&target) {
+                Ok(canonical_dir) => canonical_dir,
+                Err(err) => {
+                    self.fail(path, err.into());
+                    continue;
+                }
+            };
+            if self.visited.contains(&canonical_dir) {
+                continue;
+            }
+            self.visited.push(canonical_dir);
+            if let Err(err) = self.walk(&path, &relative_path, depth + 1) {
+                self.fail(path, err);
+            }
+        } else if metadata.is_file() && self.is_included(&relative_path) && !self.is_excluded(&relative_path) {
+            let outcome = (self.operation)(&target);
+            self.report.files.push(FileReport { path, outcome });
+        }
+    }
     Ok(())
 }
+}
 
-// Decrypt a file and overwrite it with plaintext data
-fn decrypt_file_and_save(file_path: &Path, key: &[u8]) -> io::Result<()> {
-    let mut file = File::open(file_path)?;
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer)?;
+// Apply operation to every file of folder selected by options
+fn process_folder<F>(folder_path: &Path, options: &FolderOptions, operation: F) -> Result<FolderReport, E2eError>
+where
+    F: FnMut(&Path) -> FileOutcome,
+{
+    let mut walker = FolderWalker {
+        options,
+        include: compile_patterns(&options.include)?,
+        exclude: compile_patterns(&options.exclude)?,
+        visited: vec![fs::canonicalize(folder_path)?],
+        operation,
+        report: FolderReport::default(),
+    };
+    walker.walk(folder_path, Path::new(""), 0)?;
+    Ok(walker.report)
+}
 
-    let ciphertext = match decrypt_aes128_ctr(&buffer, &IV, key) {
-        Ok(decrypted_data_record) => {decrypted_data_record}
-        Err(err) => {
-            eprintln!("Encryption error: {}", err);
-            return Err(err);
+// Create a new hidden temp file next to the target, random suffix avoids clashing with other writers
+fn create_temp_file(dir: &Path, file_name: &str) -> io::Result<(PathBuf, File)> {
+    loop {
+        let temp_path = dir.join(format!(".{}.{:016x}.tmp", file_name, rand::random::<u64>()));
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        // Owner only until the permissions of the replaced file are applied, before anything is written
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        match options.open(&temp_path) {
+            Ok(file) => return Ok((temp_path, file)),
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err),
         }
-    };
+    }
+}
+
+// Follow file_path while it is a symbolic link, a dangling link gives the path its target will be created at
+fn resolve_link(file_path: &Path) -> io::Result<PathBuf> {
+    let mut path = file_path.to_path_buf();
+    for _ in 0..MAX_LINK_HOPS {
+        match fs::symlink_metadata(&path) {
+            Ok(metadata) if metadata.file_type().is_symlink() => {
+                // A relative target is relative to the directory of the link, an absolute one replaces it
+                let target = fs::read_link(&path)?;
+                path = match path.parent() {
+                    Some(parent) => parent.join(target),
+                    None => target,
+                };
+            }
+            Ok(_) => return Ok(path),
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(path),
+            Err(err) => return Err(err),
+        }
+    }
+    Err(io::Error::new(ErrorKind::InvalidInput, "too many levels of symbolic links"))
+}
 
-    let mut decrypted_file = File::create(file_path)?;
-    decrypted_file.write_all(&ciphertext)?;
+// Give the temp file the permissions of the file it replaces, a new file keeps those of the temp file
+fn copy_permissions(file_path: &Path, temp_file: &File) -> io::Result<()> {
+    match fs::metadata(file_path) {
+        Ok(metadata) => temp_file.set_permissions(metadata.permissions()),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(err),
+    }
+}
+
+// Persist the rename itself, only possible (and needed) on unix
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    File::open(dir)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> io::Result<()> {
     Ok(())
 }
 
@@ -112,56 +510,478 @@ fn decrypt_file_and_save(file_path: &Path, key: &[u8]) -> io::Result<()> {
 /*****************************************************************************************************************
  * Start public interface for securing folder
  *****************************************************************************************************************/
-pub fn encrypt_folder(folder_path: &Path, password: &str) -> io::Result<()> {
-    let key = hash_aes_key(password);
-    for entry in fs::read_dir(folder_path)? {
-        let entry = entry?;
-        let path = entry.path();
-        if path.is_file() {
-            encrypt_file_and_save(&path, &key)?;
-        }
+// What a folder operation did with one file
+#[derive(Debug)]
+pub enum FileOutcome {
+    Encrypted,
+    Decrypted,
+    // Legacy headerless file rewritten in the current container
+    Migrated,
+    // File already starts with the container header, encrypting it again is refused
+    SkippedAlreadyEncrypted,
+    // File has no container header (plaintext or legacy file, see migrate_legacy_folder), it is left untouched
+    SkippedNotEncrypted,
+    // File is left untouched, e.g. wrong password, corrupted data or IO error
+    Failed(E2eError),
+}
+
+#[derive(Debug)]
+pub struct FileReport {
+    pub path: PathBuf,
+    pub outcome: FileOutcome,
+}
+
+// Per-file result of encrypt_folder/decrypt_folder, a failing file does not stop the others
+#[derive(Debug, Default)]
+pub struct FolderReport {
+    pub files: Vec<FileReport>,
+}
+
+impl FolderReport {
+    // True when no file failed, skipped files are not failures: after decrypt_folder, legacy files are still
+    // encrypted though reported SkippedNotEncrypted
+    pub fn is_success(&self) -> bool {
+        self.failures().next().is_none()
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &FileReport> {
+        self.files.iter().filter(|file| matches!(file.outcome, FileOutcome::Failed(_)))
+    }
+
+    // Outcome recorded for file_path, if it was part of the folder
+    pub fn outcome(&self, file_path: &Path) -> Option<&FileOutcome> {
+        self.files.iter().find(|file| file.path == file_path).map(|file| &file.outcome)
     }
-    Ok(())
 }
 
-pub fn decrypt_folder(folder_path: &Path, password: &str) -> io::Result<()> {
-    let key = hash_aes_key(password);
-    for entry in fs::read_dir(folder_path)? {
-        let entry = entry?;
-        let path = entry.path();
-        if path.is_file() {
-            decrypt_file_and_save(&path, &key)?;
-        }
+// How folder operations treat symbolic links
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SymlinkPolicy {
+    #[default]
+    Skip,
+    // The link target is processed and the link is kept, each linked directory is walked once
+    Follow,
+    // Every link is reported as a failed file
+    Error,
+}
+
+// Select the files processed by the *_with_options folder operations, default is the direct files of the folder
+// Patterns are globs matched against the path relative to the folder, "*" also matches "/" so "*.pem" selects
+// pem files at any depth and "tenant_a/*" everything under tenant_a
+#[derive(Clone, Debug, Default)]
+pub struct FolderOptions {
+    // Walk subdirectories too
+    pub recursive: bool,
+    // Only files matching one of these patterns are processed, empty selects every file
+    pub include: Vec<String>,
+    // Files matching one of these patterns are left out, a matching directory is not walked at all
+    pub exclude: Vec<String>,
+    pub symlinks: SymlinkPolicy,
+    // Number of subdirectory levels walked when recursive, None is unlimited
+    pub max_depth: Option<usize>,
+}
+
+impl FolderOptions {
+    // Walk the whole tree below the folder
+    pub fn recursive() -> Self {
+        FolderOptions { recursive: true, ..Default::default() }
     }
-    Ok(())
+}
+
+// Encrypt every file of folder, files already encrypted are skipped so calling it twice is harmless
+pub fn encrypt_folder(folder_path: &Path, password: &str) -> Result<FolderReport, E2eError> {
+    encrypt_folder_with_kdf(folder_path, password, &KdfParams::default())
+}
+
+// Same as encrypt_folder with explicit KDF and cost, each file gets its own salt
+pub fn encrypt_folder_with_kdf(folder_path: &Path, password: &str, params: &KdfParams) -> Result<FolderReport, E2eError> {
+    encrypt_folder_with_options(folder_path, password, &FolderOptions::default(), params)
+}
+
+// Same as encrypt_folder_with_kdf on the files selected by options
+pub fn encrypt_folder_with_options(folder_path: &Path, password: &str, options: &FolderOptions, params: &KdfParams) -> Result<FolderReport, E2eError> {
+    params.validate()?;
+    process_folder(folder_path, options, |path| encrypt_folder_entry(path, password, params))
+}
+
+// Decrypt every encrypted file of folder, a file is only overwritten when it decrypts with password. Headerless
+// files are skipped, legacy ones included: convert them first with migrate_legacy_folder
+pub fn decrypt_folder(folder_path: &Path, password: &str) -> Result<FolderReport, E2eError> {
+    decrypt_folder_with_options(folder_path, password, &FolderOptions::default())
+}
+
+// Same as decrypt_folder on the files selected by options
+pub fn decrypt_folder_with_options(folder_path: &Path, password: &str, options: &FolderOptions) -> Result<FolderReport, E2eError> {
+    process_folder(folder_path, options, |path| decrypt_folder_entry(path, password))
+}
+
+// Convert every legacy headerless file of folder into the current container, files carrying it are skipped.
+// A plaintext file cannot be told apart from a legacy one: only use it on a folder encrypted by an older version
+pub fn migrate_legacy_folder(folder_path: &Path, password: &str) -> Result<FolderReport, E2eError> {
+    migrate_legacy_folder_with_options(folder_path, password, &FolderOptions::default())
+}
+
+// Same as migrate_legacy_folder on the files selected by options
+pub fn migrate_legacy_folder_with_options(folder_path: &Path, password: &str, options: &FolderOptions) -> Result<FolderReport, E2eError> {
+    process_folder(folder_path, options, |path| migrate_folder_entry(path, password))
 }
 
 //this function will encrypt file and overwrite the existed file
-pub fn encrypt_file(file_path: &Path, password: &str) -> io::Result<()> {
-    let mut file = File::open(file_path)?;
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer)?;
+pub fn encrypt_file(file_path: &Path, password: &str) -> Result<(), E2eError> {
+    encrypt_file_with_kdf(file_path, password, &KdfParams::default())
+}
 
-    let key = hash_aes_key(password);
-    encrypt_file_and_save(&file_path, &key)?;
-    Ok(())
+// Same as encrypt_file with explicit KDF and cost
+pub fn encrypt_file_with_kdf(file_path: &Path, password: &str, params: &KdfParams) -> Result<(), E2eError> {
+    encrypt_file_and_save(file_path, password, params)
 }
 
 //this function will decrypt file and return the plaintext, not overwrite existed file
-pub fn decrypt_file(file_path: &Path, password: &str) -> Result<Vec<u8>, io::Error> {
-    let mut file = File::open(file_path)?;
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer)?;
+pub fn decrypt_file(file_path: &Path, password: &str) -> Result<Vec<u8>, E2eError> {
+    let result = if is_encrypted_file(file_path)? {
+        DecryptReader::new(BufReader::new(File::open(file_path)?), password).and_then(|mut reader| {
+            let mut plaintext = Vec::new();
+            reader.read_to_end(&mut plaintext)?;
+            Ok(plaintext)
+        })
+    } else {
+        open_legacy_data(fs::read(file_path)?, password)
+    };
 
-    let key = hash_aes_key(password);
-    match decrypt_aes128_ctr(&buffer, &IV, &key) {
+    match result {
         Ok(decrypted_data_record) => Ok(decrypted_data_record),
         Err(err) => {
-            eprintln!("Encryption error: {}", err);
-            return Err(err);
+            warn!("Decryption error: {}: {}", file_path.display(), err);
+            Err(err)
+        }
+    }
+}
+
+// Check whether file is already protected, i.e. starts with the container magic
+pub fn is_encrypted_file(file_path: &Path) -> Result<bool, E2eError> {
+    let mut file = File::open(file_path)?;
+    let mut magic = [0u8; 4];
+    let mut read_len = 0;
+    while read_len < magic.len() {
+        match file.read(&mut magic[read_len..])? {
+            0 => return Ok(false),
+            n => read_len += n,
         }
     }
+    Ok(is_container(&magic))
+}
+
+// Convert a legacy headerless file into the current container, files already in the container are left as is
+// Legacy files carry no integrity check: a wrong password cannot be detected, make sure it is the right one
+pub fn migrate_legacy_file(file_path: &Path, password: &str) -> Result<(), E2eError> {
+    if is_encrypted_file(file_path)? {
+        return Ok(());
+    }
+    let plaintext = open_legacy_data(fs::read(file_path)?, password)?;
+    write_encrypted_file(file_path, &plaintext, password)
+}
+
+// Write data to file_path already encrypted, so it never reaches the disk in plain. A new file is only readable
+// by its owner on unix, an existing one keeps its permissions
+pub(crate) fn write_encrypted_file(file_path: &Path, data: &[u8], password: &str) -> Result<(), E2eError> {
+    write_atomic(file_path, |encrypted_file| {
+        let mut writer = EncryptWriter::new(BufWriter::new(encrypted_file), password, &KdfParams::default())?;
+        writer.write_all(data)?;
+        writer.finish()?;
+        Ok(())
+    })
+}
+
+// Public key of a generate_pairkey_withexsecure pair, plain since this version, encrypted with the password before
+// (in the container or, for the oldest versions, in the legacy headerless format)
+pub(crate) fn read_exsecure_public_key(pub_key_path: &Path, password: &str) -> Result<Vec<u8>, E2eError> {
+    let pub_key = fs::read(pub_key_path)?;
+    if !is_container(&pub_key) && is_plain_public_key(&pub_key) {
+        return Ok(pub_key);
+    }
+    decrypt_file(pub_key_path, password)
+}
+
+// Rewrite in plain a public key encrypted by an older generate_pairkey_withexsecure, so it can be published without
+// the password. Returns false when the file is already plain. The decrypted content must be a public key, so a
+// private key given by mistake is never written in plain. Legacy files carry no integrity check: there a wrong
+// password is reported as InvalidInput
+pub fn migrate_exsecure_public_key(pub_key_path: &Path, password: &str) -> Result<bool, E2eError> {
+    let pub_key = fs::read(pub_key_path)?;
+    if !is_container(&pub_key) && is_plain_public_key(&pub_key) {
+        return Ok(false);
+    }
+    let pub_plain = decrypt_file(pub_key_path, password)?;
+    if !is_plain_public_key(&pub_plain) {
+        return Err(E2eError::InvalidInput("not a public key".to_string()));
+    }
+    write_atomic(pub_key_path, |plain_file| plain_file.write_all(&pub_plain))?;
+    Ok(true)
+}
+
+// Public key as written by generate_pairkey or export_public_key: PEM, or DER SubjectPublicKeyInfo or PKCS#1
+fn is_plain_public_key(key: &[u8]) -> bool {
+    match pem_str(key) {
+        Some(pem) => pem.trim_start().lines().next()
+            .is_some_and(|line| line.ends_with(" PUBLIC KEY-----")),
+        None => SubjectPublicKeyInfoRef::try_from(key).is_ok() || pkcs1::RsaPublicKey::try_from(key).is_ok(),
+    }
+}
+
+// Replace file content without ever leaving a truncated file behind: write is given a temp file in the same
+// directory, which is synced then renamed over file_path. Permissions of the existing file are kept, and already
+// applied to the temp file before write is called.
+// If write (or anything else) fails, the temp file is removed and file_path is untouched.
+// A symbolic link is kept, the file it points to is replaced like File::create would write it.
+pub(crate) fn write_atomic<F>(file_path: &Path, write: F) -> Result<(), E2eError>
+where
+    F: FnOnce(&mut File) -> io::Result<()>,
+{
+    let file_path = &resolve_link(file_path)?;
+    let dir = match file_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let file_name = file_path
+        .file_name()
+        .ok_or_else(|| E2eError::InvalidInput("path has no file name".to_string()))?;
+
+    let (temp_path, mut temp_file) = create_temp_file(dir, &file_name.to_string_lossy())?;
+    let result = copy_permissions(file_path, &temp_file)
+        .and_then(|_| write(&mut temp_file))
+        .and_then(|_| temp_file.sync_all())
+        .and_then(|_| {
+            drop(temp_file);
+            fs::rename(&temp_path, file_path)
+        });
+    if let Err(err) = result {
+        let _ = fs::remove_file(&temp_path);
+        return Err(err.into());
+    }
+    sync_dir(dir).map_err(E2eError::from)
+}
+
+// Encrypt everything written to it into a container on inner, using the STREAM format so memory use is constant
+// finish must be called once all data is written: without the last segment a reader reports the data as truncated
+pub struct EncryptWriter<W: Write> {
+    inner: W,
+    header: Vec<u8>,
+    encryptor: Option<EncryptorBE32<Aes256Gcm>>,
+    // Plaintext of the pending segment, only encrypted once we know whether it is the last one
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> EncryptWriter<W> {
+// Derive the key from password with fresh salt and write the container header to inner
+pub fn new(mut inner: W, password: &str, params: &KdfParams) -> Result<Self, E2eError> {
+    let (header, key) = new_stream_header(password, params)?;
+    inner.write_all(&header)?;
+
+    let nonce_prefix = &header[header.len() - STREAM_NONCE_PREFIX_LEN..];
+    let encryptor = EncryptorBE32::from_aead(Aes256Gcm::new(GenericArray::from_slice(&key)), GenericArray::from_slice(nonce_prefix));
+    Ok(EncryptWriter {
+        inner,
+        header,
+        encryptor: Some(encryptor),
+        buffer: Vec::with_capacity(STREAM_CHUNK_SIZE as usize + TAG_LEN),
+    })
+}
+
+// Encrypt the pending data as last segment, flush and give inner back
+pub fn finish(mut self) -> Result<W, E2eError> {
+    let encryptor = self.encryptor.take().ok_or_else(|| E2eError::Crypto("stream encryption already failed".to_string()))?;
+    encryptor
+        .encrypt_last_in_place(&self.header, &mut self.buffer)
+        .map_err(|_| E2eError::Crypto("AES-GCM encryption failed".to_string()))?;
+    self.inner.write_all(&self.buffer)?;
+    self.inner.flush()?;
+    Ok(self.inner)
+}
+
+fn write_segment(&mut self) -> Result<(), E2eError> {
+    let encryptor = self.encryptor.as_mut().ok_or_else(|| E2eError::Crypto("stream encryption already failed".to_string()))?;
+    if encryptor.encrypt_next_in_place(&self.header, &mut self.buffer).is_err() {
+        self.encryptor = None;
+        return Err(E2eError::Crypto("AES-GCM encryption failed".to_string()));
+    }
+    self.inner.write_all(&self.buffer)?;
+    self.buffer.clear();
+    Ok(())
+}
+}
+
+impl<W: Write> Write for EncryptWriter<W> {
+fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    let chunk_size = STREAM_CHUNK_SIZE as usize;
+    let mut written = 0;
+    while written < buf.len() {
+        // A full segment is only known not to be the last one when more data arrives
+        if self.buffer.len() == chunk_size {
+            self.write_segment()?;
+        }
+        let len = (chunk_size - self.buffer.len()).min(buf.len() - written);
+        self.buffer.extend_from_slice(&buf[written..written + len]);
+        written += len;
+    }
+    Ok(written)
+}
+
+fn flush(&mut self) -> io::Result<()> {
+    self.inner.flush()
+}
+}
+
+// Decrypt a container read from inner, only authenticated data is ever returned
+// Files of the whole-file AES-256-GCM cipher are decrypted at once when the reader is created
+pub struct DecryptReader<R: Read> {
+    inner: R,
+    header: Vec<u8>,
+    decryptor: Option<DecryptorBE32<Aes256Gcm>>,
+    chunk_size: usize,
+    // First byte of the next segment, read to find out whether the current one is the last
+    lookahead: Option<u8>,
+    plaintext: Vec<u8>,
+    position: usize,
+    finished: bool,
+}
+
+impl<R: Read> DecryptReader<R> {
+// Read the container header from inner and derive the key from password
+pub fn new(mut inner: R, password: &str) -> Result<Self, E2eError> {
+    let (header, cipher, key) = read_container_header(&mut inner, password)?;
+    let mut reader = DecryptReader {
+        inner,
+        header,
+        decryptor: None,
+        chunk_size: 0,
+        lookahead: None,
+        plaintext: Vec::new(),
+        position: 0,
+        finished: false,
+    };
+
+    if cipher == CIPHER_AES256GCM {
+        let mut ciphertext = Vec::new();
+        reader.inner.read_to_end(&mut ciphertext)?;
+        let nonce = &reader.header[reader.header.len() - NONCE_LEN..];
+        reader.plaintext = decrypt_aes256_gcm(&ciphertext, nonce, &key, &reader.header)?;
+        reader.finished = true;
+        return Ok(reader);
+    }
+
+    let fields = &reader.header[reader.header.len() - 4 - STREAM_NONCE_PREFIX_LEN..];
+    let chunk_size = u32::from_be_bytes([fields[0], fields[1], fields[2], fields[3]]);
+    if chunk_size == 0 || chunk_size > MAX_STREAM_CHUNK_SIZE {
+        return Err(E2eError::Unsupported(format!("chunk size {}", chunk_size)));
+    }
+    reader.chunk_size = chunk_size as usize;
+    reader.decryptor = Some(DecryptorBE32::from_aead(
+        Aes256Gcm::new(GenericArray::from_slice(&key)),
+        GenericArray::from_slice(&fields[4..]),
+    ));
+    Ok(reader)
+}
+
+// Read and authenticate the next segment into plaintext
+fn read_segment(&mut self) -> Result<(), E2eError> {
+    let segment_len = self.chunk_size + TAG_LEN;
+    let mut segment = Vec::with_capacity(segment_len);
+    segment.extend(self.lookahead.take());
+    (&mut self.inner).take((segment_len - segment.len()) as u64).read_to_end(&mut segment)?;
+
+    // The last segment is the one followed by end of data
+    let mut next = [0u8; 1];
+    let is_last = segment.len() < segment_len || loop {
+        match self.inner.read(&mut next) {
+            Ok(0) => break true,
+            Ok(_) => break false,
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err.into()),
+        }
+    };
+
+    let mut decryptor = self.decryptor.take().ok_or(E2eError::WrongPassword)?;
+    if is_last {
+        decryptor.decrypt_last_in_place(&self.header, &mut segment).map_err(|_| E2eError::WrongPassword)?;
+        self.finished = true;
+    } else {
+        decryptor.decrypt_next_in_place(&self.header, &mut segment).map_err(|_| E2eError::WrongPassword)?;
+        self.decryptor = Some(decryptor);
+        self.lookahead = Some(next[0]);
+    }
+    self.plaintext = segment;
+    self.position = 0;
+    Ok(())
+}
+}
+
+impl<R: Read> Read for DecryptReader<R> {
+fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    while self.position == self.plaintext.len() {
+        if self.finished {
+            return Ok(0);
+        }
+        self.read_segment()?;
+    }
+    let len = buf.len().min(self.plaintext.len() - self.position);
+    buf[..len].copy_from_slice(&self.plaintext[self.position..self.position + len]);
+    self.position += len;
+    Ok(len)
+}
 }
 /*****************************************************************************************************************
  * End public interface for securing folder
- *****************************************************************************************************************/
\ No newline at end of file
+ *****************************************************************************************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_write_atomic_failed_write() {
+        let dir = TempDir::new("test_write_atomic_failed_write").unwrap();
+        let content: &[u8] = b"test_write_atomic_failed_write Hello world!";
+        let file_path = dir.path().join("test.txt");
+        fs::write(&file_path, content).unwrap();
+
+        // Simulate a crash or a full disk in the middle of the write
+        let result = write_atomic(&file_path, |file| {
+            file.write_all(b"partial")?;
+            Err(io::Error::new(ErrorKind::StorageFull, "disk full"))
+        });
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::StorageFull);
+
+        // Original content is untouched and no temp file is left behind
+        assert_eq!(fs::read(&file_path).unwrap(), content);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_atomic_temp_file_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new("test_write_atomic_temp_file_permissions").unwrap();
+        let file_path = dir.path().join("private_key.pem");
+        fs::write(&file_path, b"test_write_atomic_temp_file_permissions Hello world!").unwrap();
+
+        // The content is never readable by others, not even in the temp file while it is written
+        for mode in [0o600, 0o640] {
+            fs::set_permissions(&file_path, fs::Permissions::from_mode(mode)).unwrap();
+            write_atomic(&file_path, |temp_file| {
+                assert_eq!(temp_file.metadata()?.permissions().mode() & 0o777, mode);
+                temp_file.write_all(b"test_write_atomic_temp_file_permissions Hello world!")
+            }).unwrap();
+            assert_eq!(fs::metadata(&file_path).unwrap().permissions().mode() & 0o777, mode);
+        }
+
+        // A new file stays owner only
+        let new_path = dir.path().join("new_key.pem");
+        write_atomic(&new_path, |temp_file| {
+            assert_eq!(temp_file.metadata()?.permissions().mode() & 0o777, 0o600);
+            Ok(())
+        }).unwrap();
+        assert_eq!(fs::metadata(&new_path).unwrap().permissions().mode() & 0o777, 0o600);
+    }
+}
@@ -1,7 +1,14 @@
 mod e2e_implementation;
+mod e2e_x25519;
+mod encrypted_key;
+mod envelope;
+mod error;
 mod exsecure;
 mod c_wrapper;
 
 pub use e2e_implementation::*;
+pub use e2e_x25519::*;
+pub use encrypted_key::Pkcs8Kdf;
+pub use error::*;
 pub use exsecure::*;
 pub use c_wrapper::*;
\ No newline at end of file
@@ -22,14 +22,33 @@ static int test_e2e_basicfunc(const char *out_dir) {
     snprintf(priv_key_path, sizeof(priv_key_path), "%s/private.pem", out_dir);
     snprintf(pub_key_path, sizeof(pub_key_path), "%s/public.pem", out_dir);
 
-    if (!e2e_generate_pairkey(instance, priv_key_path, pub_key_path)) {
-        printf("Failed to generate key pair\n");
+    // Encrypting before init reports a status and a message
+    unsigned char early_message[BUFFER_SIZE];
+    size_t early_message_len = 0;
+    if (e2e_encrypt(instance, (const unsigned char*)"early", 5, early_message, &early_message_len) != E2E_ERR_NOT_INITIALIZED
+        || e2e_last_error() != E2E_ERR_NOT_INITIALIZED
+        || strlen(e2e_last_error_message()) == 0) {
+        printf("Encrypt before init should fail with E2E_ERR_NOT_INITIALIZED\n");
         e2e_free(instance);
         return -1;
     }
 
-    if (!e2e_init(instance, priv_key_path, pub_key_path)) {
-        printf("Failed to initialize with key files\n");
+    if (e2e_generate_pairkey(instance, priv_key_path, pub_key_path) != E2E_OK) {
+        printf("Failed to generate key pair: %s\n", e2e_last_error_message());
+        e2e_free(instance);
+        return -1;
+    }
+
+    if (e2e_init(instance, priv_key_path, pub_key_path) != E2E_OK) {
+        printf("Failed to initialize with key files: %s\n", e2e_last_error_message());
+        e2e_free(instance);
+        remove(priv_key_path);
+        remove(pub_key_path);
+        return -1;
+    }
+
+    if (e2e_key_size(instance) != 2048) {
+        printf("Unexpected key size %zu\n", e2e_key_size(instance));
         e2e_free(instance);
         remove(priv_key_path);
         remove(pub_key_path);
@@ -37,10 +56,10 @@ static int test_e2e_basicfunc(const char *out_dir) {
     }
 
     const char* message = "C wrapper test_e2e_basicfunc is passed";
-    unsigned char encrypted_message[256];
-    size_t encrypted_message_len = 256;
+    unsigned char encrypted_message[BUFFER_SIZE * 2];
+    size_t encrypted_message_len = sizeof(encrypted_message);
 
-    if (!e2e_encrypt(instance, (const unsigned char*)message, strlen(message), encrypted_message, &encrypted_message_len)) {
+    if (e2e_encrypt(instance, (const unsigned char*)message, strlen(message), encrypted_message, &encrypted_message_len) != E2E_OK) {
         printf("Failed to encrypt message\n");
         e2e_free(instance);
         remove(priv_key_path);
@@ -51,7 +70,7 @@ static int test_e2e_basicfunc(const char *out_dir) {
     unsigned char decrypted_message[256];
     size_t decrypted_message_len = 256;
 
-    if (!e2e_decrypt(instance, encrypted_message, encrypted_message_len, decrypted_message, &decrypted_message_len)) {
+    if (e2e_decrypt(instance, encrypted_message, encrypted_message_len, decrypted_message, &decrypted_message_len) != E2E_OK) {
         printf("Failed to decrypt message\n");
         e2e_free(instance);
         remove(priv_key_path);
@@ -87,38 +106,906 @@ static int test_e2e_exsecure(const char *out_dir) {
     printf("encrypt folder %s\n", folder_path);
 
     // Encrypt the folder
-    if (e2e_encrypt_folder(folder_path, password) == false) {
+    if (e2e_encrypt_folder(folder_path, password) != E2E_OK) {
         printf("Failed to encrypt folder\n");
         return -1;
     }
 
     // Decrypt the folder
-    if (e2e_decrypt_folder(folder_path, password) == false) {
+    if (e2e_decrypt_folder(folder_path, password) != E2E_OK) {
         printf("Failed to decrypt folder\n");
         return -1;
     }
 
     //test decrypt file
     // firstly, encrypt all files in folder again
-    if (e2e_encrypt_folder(folder_path, password) == false) {
+    if (e2e_encrypt_folder(folder_path, password) != E2E_OK) {
         printf("Failed to encrypt folder\n");
         return -1;
     }
+    // Encrypting again skips files which are already encrypted
+    if (e2e_encrypt_folder(folder_path, password) != E2E_OK) {
+        printf("Failed to encrypt folder twice\n");
+        return -1;
+    }
+    // Wrong password is reported and leaves files encrypted
+    if (e2e_decrypt_folder(folder_path, "wrong password") != E2E_ERR_WRONG_PASSWORD) {
+        printf("Decrypt folder with wrong password should fail with E2E_ERR_WRONG_PASSWORD\n");
+        return -1;
+    }
+    printf("Wrong password reported: %s\n", e2e_last_error_message());
     // Decrypt a file and print content
     char file_path[BUFFER_SIZE];
-    char decrypted_data[BUFFER_SIZE];
+    unsigned char decrypted_data[BUFFER_SIZE];
+    size_t decrypted_data_len = 4;
     memset(folder_path, 0, BUFFER_SIZE);
     memset(decrypted_data, 0, BUFFER_SIZE);
     snprintf(file_path, sizeof(file_path), "%s/test_folder/test1.txt", out_dir);
 
-    if (e2e_decrypt_file(file_path, password, decrypted_data) == -1) {
-        printf("Failed to decrypt file\n");
+    // Too small buffer gives the required size
+    if (e2e_decrypt_file(file_path, password, decrypted_data, &decrypted_data_len) != E2E_ERR_BUFFER_TOO_SMALL
+        || decrypted_data_len <= 4 || decrypted_data_len > sizeof(decrypted_data)) {
+        printf("Too small buffer should be reported with the required size\n");
+        return -1;
+    }
+    if (e2e_decrypt_file(file_path, password, decrypted_data, &decrypted_data_len) != E2E_OK) {
+        printf("Failed to decrypt file: %s\n", e2e_last_error_message());
         return -1;
     }
 
     // Print decrypted content
-    printf("Decrypted data file successfully: %s\n", (char*)decrypted_data);
+    printf("Decrypted data file successfully: %.*s\n", (int)decrypted_data_len, (const char*)decrypted_data);
+
+    return 0;
+}
+
+//test 2
+static int test_e2e_padding(const char *out_dir) {
+    int ret = -1;
+    char priv_key_path[BUFFER_SIZE];
+    char pub_key_path[BUFFER_SIZE];
+    snprintf(priv_key_path, sizeof(priv_key_path), "%s/private_padding.pem", out_dir);
+    snprintf(pub_key_path, sizeof(pub_key_path), "%s/public_padding.pem", out_dir);
+
+    E2eRSA2K* oaep_instance = e2e_new_with_padding(E2E_PADDING_OAEP_SHA256);
+    E2eRSA2K* pkcs1_instance = e2e_new();
+    if (!oaep_instance || !pkcs1_instance) {
+        printf("Failed to create E2eRSA2K instance\n");
+        goto cleanup;
+    }
+    if (e2e_new_with_padding((e2e_padding)42) != NULL || e2e_last_error() != E2E_ERR_INVALID_ARGUMENT) {
+        printf("Unknown padding must be rejected\n");
+        goto cleanup;
+    }
+
+    if (e2e_generate_pairkey(oaep_instance, priv_key_path, pub_key_path) != E2E_OK
+        || e2e_init(oaep_instance, priv_key_path, pub_key_path) != E2E_OK
+        || e2e_init(pkcs1_instance, priv_key_path, pub_key_path) != E2E_OK) {
+        printf("Failed to set up key pair\n");
+        goto cleanup;
+    }
+
+    const char* message = "C wrapper test_e2e_padding is passed";
+    unsigned char encrypted_message[BUFFER_SIZE * 2];
+    size_t encrypted_message_len = sizeof(encrypted_message);
+    unsigned char decrypted_message[BUFFER_SIZE];
+    size_t decrypted_message_len = sizeof(decrypted_message);
+
+    // Encrypt with OAEP, decrypt with an instance configured for PKCS#1 v1.5
+    if (e2e_encrypt(oaep_instance, (const unsigned char*)message, strlen(message), encrypted_message, &encrypted_message_len) != E2E_OK
+        || e2e_decrypt(pkcs1_instance, encrypted_message, encrypted_message_len, decrypted_message, &decrypted_message_len) != E2E_OK) {
+        printf("Failed to encrypt/decrypt with OAEP padding\n");
+        goto cleanup;
+    }
+
+    if (decrypted_message_len != strlen(message) || memcmp(decrypted_message, message, decrypted_message_len) != 0) {
+        printf("Decrypted OAEP message does not match\n");
+        goto cleanup;
+    }
+    printf("Decrypted OAEP message successfully: %.*s\n", (int)decrypted_message_len, decrypted_message);
+
+    // The OAEP instance refuses PKCS#1 v1.5 messages until legacy padding is accepted
+    encrypted_message_len = sizeof(encrypted_message);
+    decrypted_message_len = sizeof(decrypted_message);
+    if (e2e_encrypt(pkcs1_instance, (const unsigned char*)message, strlen(message), encrypted_message, &encrypted_message_len) != E2E_OK
+        || e2e_decrypt(oaep_instance, encrypted_message, encrypted_message_len, decrypted_message, &decrypted_message_len) != E2E_ERR_UNSUPPORTED) {
+        printf("OAEP instance should refuse PKCS#1 v1.5 messages\n");
+        goto cleanup;
+    }
+    decrypted_message_len = sizeof(decrypted_message);
+    if (e2e_set_accept_legacy_padding(oaep_instance, true) != E2E_OK
+        || e2e_decrypt(oaep_instance, encrypted_message, encrypted_message_len, decrypted_message, &decrypted_message_len) != E2E_OK
+        || e2e_set_accept_legacy_padding(NULL, true) != E2E_ERR_INVALID_ARGUMENT) {
+        printf("Failed to decrypt PKCS#1 v1.5 message with legacy padding accepted\n");
+        goto cleanup;
+    }
+    ret = 0;
+
+cleanup:
+    e2e_free(oaep_instance);
+    e2e_free(pkcs1_instance);
+    remove(priv_key_path);
+    remove(pub_key_path);
+    return ret;
+}
+
+//test 3
+static int test_e2e_signature(const char *out_dir) {
+    int ret = -1;
+    char priv_key_path[BUFFER_SIZE];
+    char pub_key_path[BUFFER_SIZE];
+    snprintf(priv_key_path, sizeof(priv_key_path), "%s/private_signature.pem", out_dir);
+    snprintf(pub_key_path, sizeof(pub_key_path), "%s/public_signature.pem", out_dir);
+
+    E2eRSA2K* instance = e2e_new();
+    if (!instance) {
+        printf("Failed to create E2eRSA2K instance\n");
+        return -1;
+    }
+
+    if (e2e_generate_pairkey(instance, priv_key_path, pub_key_path) != E2E_OK
+        || e2e_init(instance, priv_key_path, pub_key_path) != E2E_OK) {
+        printf("Failed to set up key pair\n");
+        goto cleanup;
+    }
+
+    const char* message = "C wrapper test_e2e_signature is passed";
+    unsigned char signature[BUFFER_SIZE];
+    size_t signature_len = sizeof(signature);
+
+    if (e2e_sign(instance, (const unsigned char*)message, strlen(message), signature, &signature_len) != E2E_OK) {
+        printf("Failed to sign message\n");
+        goto cleanup;
+    }
+    if (e2e_verify(instance, (const unsigned char*)message, strlen(message), signature, signature_len) != E2E_OK) {
+        printf("Failed to verify signature\n");
+        goto cleanup;
+    }
+
+    // Wrong scheme or modified signature must not verify
+    if (e2e_set_signature_scheme(instance, E2E_SIGNATURE_PKCS1V15_SHA256) != E2E_OK
+        || e2e_verify(instance, (const unsigned char*)message, strlen(message), signature, signature_len) == E2E_OK) {
+        printf("PSS signature accepted as PKCS#1 v1.5\n");
+        goto cleanup;
+    }
+    e2e_set_signature_scheme(instance, E2E_SIGNATURE_PSS_SHA256);
+    signature[0] ^= 0x01;
+    if (e2e_verify(instance, (const unsigned char*)message, strlen(message), signature, signature_len) != E2E_ERR_INVALID_SIGNATURE) {
+        printf("Tampered signature accepted\n");
+        goto cleanup;
+    }
+
+    printf("%s\n", message);
+    ret = 0;
+
+cleanup:
+    e2e_free(instance);
+    remove(priv_key_path);
+    remove(pub_key_path);
+    return ret;
+}
+
+//test 4
+static int test_e2e_x25519(const char *out_dir) {
+    int ret = -1;
+    char priv_key_path[BUFFER_SIZE];
+    char pub_key_path[BUFFER_SIZE];
+    snprintf(priv_key_path, sizeof(priv_key_path), "%s/private_x25519.pem", out_dir);
+    snprintf(pub_key_path, sizeof(pub_key_path), "%s/public_x25519.pem", out_dir);
+
+    E2eHandle* instance = e2e_new_x25519(E2E_AEAD_CHACHA20POLY1305);
+    if (!instance) {
+        printf("Failed to create X25519 instance\n");
+        return -1;
+    }
+
+    if (e2e_generate_pairkey(instance, priv_key_path, pub_key_path) != E2E_OK
+        || e2e_init(instance, priv_key_path, pub_key_path) != E2E_OK) {
+        printf("Failed to set up X25519 key pair\n");
+        goto cleanup;
+    }
+
+    const char* message = "C wrapper test_e2e_x25519 is passed";
+    unsigned char encrypted_message[BUFFER_SIZE];
+    size_t encrypted_message_len = sizeof(encrypted_message);
+    unsigned char decrypted_message[BUFFER_SIZE];
+    size_t decrypted_message_len = sizeof(decrypted_message);
+
+    if (e2e_encrypt(instance, (const unsigned char*)message, strlen(message), encrypted_message, &encrypted_message_len) != E2E_OK
+        || e2e_decrypt(instance, encrypted_message, encrypted_message_len, decrypted_message, &decrypted_message_len) != E2E_OK) {
+        printf("Failed to encrypt/decrypt with X25519\n");
+        goto cleanup;
+    }
+
+    // RSA only functions are refused
+    if (e2e_set_padding(instance, E2E_PADDING_OAEP_SHA256) != E2E_ERR_UNSUPPORTED || e2e_key_size(instance) != 0) {
+        printf("RSA function accepted by X25519 instance\n");
+        goto cleanup;
+    }
+
+    if (decrypted_message_len == strlen(message) && memcmp(decrypted_message, message, decrypted_message_len) == 0) {
+        printf("Decrypted X25519 message successfully: %.*s\n", (int)decrypted_message_len, decrypted_message);
+        ret = 0;
+    }
+
+cleanup:
+    e2e_free(instance);
+    remove(priv_key_path);
+    remove(pub_key_path);
+    return ret;
+}
+
+//test 5
+static int test_e2e_folder_options(const char *out_dir) {
+    const char* password = "testpassword";
+    char folder_path[BUFFER_SIZE];
+    snprintf(folder_path, sizeof(folder_path), "%s/test_folder_options", out_dir);
+
+    const char* include[] = { "*.pem" };
+    const char* exclude[] = { "skip" };
+    e2e_folder_options options;
+    memset(&options, 0, sizeof(options));
+    options.recursive = true;
+    options.include = include;
+    options.include_count = 1;
+    options.exclude = exclude;
+    options.exclude_count = 1;
+    options.symlinks = E2E_SYMLINK_SKIP;
+    options.max_depth = 1;
+
+    // Unknown symlink policy is rejected before touching any file
+    options.symlinks = 7;
+    if (e2e_encrypt_folder_with_options(folder_path, password, &options) != E2E_ERR_INVALID_ARGUMENT) {
+        printf("Invalid symlink policy should be rejected\n");
+        return -1;
+    }
+    options.symlinks = E2E_SYMLINK_SKIP;
+
+    // Selected files are checked by the Rust side of the test
+    if (e2e_encrypt_folder_with_options(folder_path, password, &options) != E2E_OK) {
+        printf("Failed to encrypt folder with options\n");
+        return -1;
+    }
+    return 0;
+}
+
+//test 6
+static int test_e2e_null_safety(const char *out_dir) {
+    int ret = -1;
+    const char* invalid_utf8 = "\xff\xfe.pem";
+    const char* message = "C wrapper test_e2e_null_safety is passed";
+    unsigned char buffer[BUFFER_SIZE];
+    size_t buffer_len = 0;
+    char priv_key_path[BUFFER_SIZE];
+    char pub_key_path[BUFFER_SIZE];
+    snprintf(priv_key_path, sizeof(priv_key_path), "%s/private_null.pem", out_dir);
+    snprintf(pub_key_path, sizeof(pub_key_path), "%s/public_null.pem", out_dir);
+
+    // NULL handle
+    if (e2e_init(NULL, priv_key_path, pub_key_path) != E2E_ERR_INVALID_ARGUMENT
+        || e2e_encrypt(NULL, (const unsigned char*)message, strlen(message), buffer, &buffer_len) != E2E_ERR_INVALID_ARGUMENT
+        || e2e_set_padding(NULL, E2E_PADDING_OAEP_SHA256) != E2E_ERR_INVALID_ARGUMENT
+        || e2e_key_size(NULL) != 0 || e2e_last_error() != E2E_ERR_INVALID_ARGUMENT) {
+        printf("NULL handle should be rejected\n");
+        return -1;
+    }
+    printf("NULL handle reported: %s\n", e2e_last_error_message());
+    e2e_free(NULL);
+
+    E2eHandle* instance = e2e_new();
+    if (!instance) {
+        printf("Failed to create E2eHandle instance\n");
+        return -1;
+    }
+
+    // NULL or invalid UTF-8 strings
+    if (e2e_generate_pairkey(instance, NULL, pub_key_path) != E2E_ERR_INVALID_ARGUMENT
+        || e2e_init(instance, priv_key_path, NULL) != E2E_ERR_INVALID_ARGUMENT
+        || e2e_init(instance, invalid_utf8, pub_key_path) != E2E_ERR_INVALID_ARGUMENT
+        || e2e_init_withexsecure(instance, priv_key_path, pub_key_path, NULL) != E2E_ERR_INVALID_ARGUMENT
+        || e2e_encrypt_folder(NULL, "password") != E2E_ERR_INVALID_ARGUMENT
+        || e2e_decrypt_folder(out_dir, invalid_utf8) != E2E_ERR_INVALID_ARGUMENT
+        || e2e_decrypt_file(priv_key_path, NULL, buffer, &buffer_len) != E2E_ERR_INVALID_ARGUMENT) {
+        printf("NULL or invalid UTF-8 string should be rejected\n");
+        goto cleanup;
+    }
+
+    // NULL or invalid UTF-8 folder patterns
+    const char* patterns[] = { NULL };
+    e2e_folder_options options;
+    memset(&options, 0, sizeof(options));
+    options.include = patterns;
+    options.include_count = 1;
+    if (e2e_encrypt_folder_with_options(out_dir, "password", &options) != E2E_ERR_INVALID_ARGUMENT) {
+        printf("NULL pattern should be rejected\n");
+        goto cleanup;
+    }
+    patterns[0] = invalid_utf8;
+    if (e2e_encrypt_folder_with_options(out_dir, "password", &options) != E2E_ERR_INVALID_ARGUMENT) {
+        printf("Invalid UTF-8 pattern should be rejected\n");
+        goto cleanup;
+    }
+
+    // NULL buffers
+    if (e2e_generate_pairkey(instance, priv_key_path, pub_key_path) != E2E_OK
+        || e2e_init(instance, priv_key_path, pub_key_path) != E2E_OK) {
+        printf("Failed to initialize key pair: %s\n", e2e_last_error_message());
+        goto cleanup;
+    }
+    if (e2e_encrypt(instance, NULL, 10, buffer, &buffer_len) != E2E_ERR_INVALID_ARGUMENT
+        || e2e_encrypt(instance, (const unsigned char*)message, strlen(message), NULL, &buffer_len) != E2E_ERR_INVALID_ARGUMENT
+        || e2e_encrypt(instance, (const unsigned char*)message, strlen(message), buffer, NULL) != E2E_ERR_INVALID_ARGUMENT
+        || e2e_sign(instance, (const unsigned char*)message, strlen(message), NULL, &buffer_len) != E2E_ERR_INVALID_ARGUMENT
+        || e2e_verify(instance, (const unsigned char*)message, strlen(message), NULL, 256) != E2E_ERR_INVALID_ARGUMENT) {
+        printf("NULL buffer should be rejected\n");
+        goto cleanup;
+    }
+    // An empty message may be passed as NULL
+    buffer_len = sizeof(buffer);
+    if (e2e_encrypt(instance, NULL, 0, buffer, &buffer_len) != E2E_OK || strlen(e2e_last_error_message()) != 0) {
+        printf("Failed to encrypt empty message: %s\n", e2e_last_error_message());
+        goto cleanup;
+    }
+
+    printf("%s\n", message);
+    ret = 0;
+
+cleanup:
+    e2e_free(instance);
+    remove(priv_key_path);
+    remove(pub_key_path);
+    return ret;
+}
+
+//test 7
+static int test_e2e_buffer_size(const char *out_dir) {
+    int ret = -1;
+    char priv_key_path[BUFFER_SIZE];
+    char pub_key_path[BUFFER_SIZE];
+    snprintf(priv_key_path, sizeof(priv_key_path), "%s/private_buffer_size.pem", out_dir);
+    snprintf(pub_key_path, sizeof(pub_key_path), "%s/public_buffer_size.pem", out_dir);
+
+    const char* message = "C wrapper test_e2e_buffer_size is passed";
+    size_t encrypted_size = 0;
+
+    // RSA size depends on the key, it is unknown before init
+    E2eHandle* rsa_instance = e2e_new();
+    if (!rsa_instance || e2e_encrypted_size(rsa_instance, strlen(message), &encrypted_size) != E2E_ERR_NOT_INITIALIZED) {
+        printf("RSA encrypted size should need init\n");
+        e2e_free(rsa_instance);
+        return -1;
+    }
+    e2e_free(rsa_instance);
+
+    E2eHandle* instance = e2e_new_x25519(E2E_AEAD_CHACHA20POLY1305);
+    unsigned char* encrypted_message = NULL;
+    if (!instance
+        || e2e_generate_pairkey(instance, priv_key_path, pub_key_path) != E2E_OK
+        || e2e_init(instance, priv_key_path, pub_key_path) != E2E_OK
+        || e2e_encrypted_size(instance, strlen(message), &encrypted_size) != E2E_OK) {
+        printf("Failed to get encrypted size: %s\n", e2e_last_error_message());
+        goto cleanup;
+    }
+
+    // One byte short is refused and nothing is written
+    encrypted_message = malloc(encrypted_size);
+    size_t encrypted_message_len = encrypted_size - 1;
+    memset(encrypted_message, 0, encrypted_size);
+    if (e2e_encrypt(instance, (const unsigned char*)message, strlen(message), encrypted_message, &encrypted_message_len) != E2E_ERR_BUFFER_TOO_SMALL
+        || encrypted_message_len != encrypted_size || encrypted_message[0] != 0) {
+        printf("Too small encrypt buffer should be reported with the required size\n");
+        goto cleanup;
+    }
+    // Exact size fits
+    if (e2e_encrypt(instance, (const unsigned char*)message, strlen(message), encrypted_message, &encrypted_message_len) != E2E_OK
+        || encrypted_message_len != encrypted_size) {
+        printf("Failed to encrypt with the exact size: %s\n", e2e_last_error_message());
+        goto cleanup;
+    }
+
+    unsigned char decrypted_message[BUFFER_SIZE];
+    size_t decrypted_message_len = 3;
+    if (e2e_decrypt(instance, encrypted_message, encrypted_message_len, decrypted_message, &decrypted_message_len) != E2E_ERR_BUFFER_TOO_SMALL
+        || decrypted_message_len != strlen(message)) {
+        printf("Too small decrypt buffer should be reported with the required size\n");
+        goto cleanup;
+    }
+    if (e2e_decrypt(instance, encrypted_message, encrypted_message_len, decrypted_message, &decrypted_message_len) != E2E_OK
+        || decrypted_message_len != strlen(message) || memcmp(decrypted_message, message, decrypted_message_len) != 0) {
+        printf("Failed to decrypt with the exact size: %s\n", e2e_last_error_message());
+        goto cleanup;
+    }
+
+    printf("%s\n", message);
+    ret = 0;
+
+cleanup:
+    free(encrypted_message);
+    e2e_free(instance);
+    remove(priv_key_path);
+    remove(pub_key_path);
+    return ret;
+}
+
+//test 8
+static int test_e2e_alloc(const char *out_dir) {
+    int ret = -1;
+    char priv_key_path[BUFFER_SIZE];
+    char pub_key_path[BUFFER_SIZE];
+    char file_path[BUFFER_SIZE];
+    snprintf(priv_key_path, sizeof(priv_key_path), "%s/private_alloc.pem", out_dir);
+    snprintf(pub_key_path, sizeof(pub_key_path), "%s/public_alloc.pem", out_dir);
+    snprintf(file_path, sizeof(file_path), "%s/alloc_test.bin", out_dir);
+
+    // Same content as the file encrypted by the Rust side of the test
+    const unsigned char file_content[] = { 'k', 'e', 'y', 0x00, 0x01, 0x02, 0x00, 'e', 'n', 'd' };
+    const char* message = "C wrapper test_e2e_alloc is passed";
+    e2e_buffer encrypted = { NULL, 0 };
+    e2e_buffer decrypted = { NULL, 0 };
+    e2e_buffer file = { NULL, 0 };
+
+    E2eHandle* instance = e2e_new_x25519(E2E_AEAD_AES256GCM);
+    if (!instance
+        || e2e_generate_pairkey(instance, priv_key_path, pub_key_path) != E2E_OK
+        || e2e_init(instance, priv_key_path, pub_key_path) != E2E_OK) {
+        printf("Failed to initialize key pair: %s\n", e2e_last_error_message());
+        goto cleanup;
+    }
+
+    size_t encrypted_size = 0;
+    if (e2e_encrypt_alloc(instance, (const unsigned char*)message, strlen(message), &encrypted) != E2E_OK
+        || e2e_encrypted_size(instance, strlen(message), &encrypted_size) != E2E_OK
+        || encrypted.data == NULL || encrypted.len != encrypted_size) {
+        printf("Failed to encrypt into an allocated buffer: %s\n", e2e_last_error_message());
+        goto cleanup;
+    }
+    if (e2e_decrypt_alloc(instance, encrypted.data, encrypted.len, &decrypted) != E2E_OK
+        || decrypted.len != strlen(message) || memcmp(decrypted.data, message, decrypted.len) != 0) {
+        printf("Failed to decrypt into an allocated buffer: %s\n", e2e_last_error_message());
+        goto cleanup;
+    }
+
+    // Failure leaves an empty buffer
+    encrypted.data[encrypted.len - 1] ^= 0x01;
+    e2e_buffer_free(&decrypted);
+    if (e2e_decrypt_alloc(instance, encrypted.data, encrypted.len, &decrypted) != E2E_ERR_DECRYPTION_FAILED
+        || decrypted.data != NULL || decrypted.len != 0) {
+        printf("Failed decrypt should leave an empty buffer\n");
+        goto cleanup;
+    }
+
+    // Binary file content is returned whole, zero bytes included
+    unsigned char file_buffer[BUFFER_SIZE];
+    size_t file_buffer_len = sizeof(file_buffer);
+    if (e2e_decrypt_file(file_path, "testpassword", file_buffer, &file_buffer_len) != E2E_OK
+        || file_buffer_len != sizeof(file_content) || memcmp(file_buffer, file_content, file_buffer_len) != 0) {
+        printf("Failed to decrypt binary file: %s\n", e2e_last_error_message());
+        goto cleanup;
+    }
+    if (e2e_decrypt_file_alloc(file_path, "testpassword", &file) != E2E_OK
+        || file.len != sizeof(file_content) || memcmp(file.data, file_content, file.len) != 0) {
+        printf("Failed to decrypt file into an allocated buffer: %s\n", e2e_last_error_message());
+        goto cleanup;
+    }
+
+    // Freeing twice or freeing NULL is harmless
+    e2e_buffer_free(&file);
+    e2e_buffer_free(&file);
+    e2e_buffer_free(NULL);
+    // Deprecated entry point, still exported and doing nothing
+    e2e_free_data(NULL);
+    if (file.data != NULL || file.len != 0) {
+        printf("Freed buffer should be reset\n");
+        goto cleanup;
+    }
+
+    printf("%s\n", message);
+    ret = 0;
+
+cleanup:
+    e2e_buffer_free(&encrypted);
+    e2e_buffer_free(&decrypted);
+    e2e_buffer_free(&file);
+    e2e_free(instance);
+    remove(priv_key_path);
+    remove(pub_key_path);
+    return ret;
+}
+
+//test 9
+typedef struct log_capture {
+    int count;
+    e2e_log_level level;
+    char message[BUFFER_SIZE];
+} log_capture;
+
+static void capture_log(e2e_log_level level, const char* message, void* user_data) {
+    log_capture* capture = (log_capture*)user_data;
+    capture->count++;
+    capture->level = level;
+    snprintf(capture->message, sizeof(capture->message), "%s", message);
+}
+
+static void unset_log_in_callback(e2e_log_level level, const char* message, void* user_data) {
+    capture_log(level, message, user_data);
+    // Calling back into the SDK is allowed, here to remove the callback itself
+    e2e_set_log_callback(NULL, E2E_LOG_TRACE, NULL);
+}
+
+static int test_e2e_log_callback(const char *out_dir) {
+    char file_path[BUFFER_SIZE];
+    unsigned char buffer[BUFFER_SIZE];
+    size_t buffer_len = sizeof(buffer);
+    log_capture capture;
+    memset(&capture, 0, sizeof(capture));
+
+    // Silent by default, the Rust side of the test checks nothing reached stdout
+    snprintf(file_path, sizeof(file_path), "%s/silent_missing.bin", out_dir);
+    e2e_decrypt_file(file_path, "testpassword", buffer, &buffer_len);
+
+    if (e2e_set_log_callback(capture_log, (e2e_log_level)42, &capture) != E2E_ERR_INVALID_ARGUMENT) {
+        printf("Unknown log level should be rejected\n");
+        return -1;
+    }
+    if (e2e_set_log_callback(capture_log, E2E_LOG_DEBUG, &capture) != E2E_OK) {
+        printf("Failed to set log callback: %s\n", e2e_last_error_message());
+        return -1;
+    }
+    snprintf(file_path, sizeof(file_path), "%s/logged_missing.bin", out_dir);
+    e2e_decrypt_file(file_path, "testpassword", buffer, &buffer_len);
+    if (capture.count == 0 || strstr(capture.message, "logged_missing.bin") == NULL) {
+        printf("Log callback was not called\n");
+        return -1;
+    }
+
+    // Debug messages are filtered out at a lower level
+    int count = capture.count;
+    e2e_set_log_callback(capture_log, E2E_LOG_ERROR, &capture);
+    e2e_decrypt_file(file_path, "testpassword", buffer, &buffer_len);
+    if (capture.count != count) {
+        printf("Message above the max level was logged: %s\n", capture.message);
+        return -1;
+    }
+
+    // A callback can replace itself without deadlock, later messages are not given to it
+    e2e_set_log_callback(unset_log_in_callback, E2E_LOG_DEBUG, &capture);
+    e2e_decrypt_file(file_path, "testpassword", buffer, &buffer_len);
+    e2e_decrypt_file(file_path, "testpassword", buffer, &buffer_len);
+    if (capture.count != count + 1) {
+        printf("Callback removing itself got %d messages\n", capture.count - count);
+        return -1;
+    }
+    count = capture.count;
+
+    // NULL callback silences the SDK again
+    e2e_set_log_callback(capture_log, E2E_LOG_TRACE, &capture);
+    e2e_set_log_callback(NULL, E2E_LOG_TRACE, NULL);
+    e2e_decrypt_file(file_path, "testpassword", buffer, &buffer_len);
+    if (capture.count != count) {
+        printf("Message logged without callback: %s\n", capture.message);
+        return -1;
+    }
+    return 0;
+}
+
+//test 10
+static int test_e2e_init_public_private(const char *out_dir) {
+    int ret = -1;
+    char priv_key_path[BUFFER_SIZE];
+    char pub_key_path[BUFFER_SIZE];
+    snprintf(priv_key_path, sizeof(priv_key_path), "%s/private_peer.pem", out_dir);
+    snprintf(pub_key_path, sizeof(pub_key_path), "%s/public_peer.pem", out_dir);
+
+    const char* message = "C wrapper test_e2e_init_public_private is passed";
+    unsigned char encrypted_message[BUFFER_SIZE];
+    size_t encrypted_message_len = sizeof(encrypted_message);
+    unsigned char decrypted_message[BUFFER_SIZE];
+    size_t decrypted_message_len = sizeof(decrypted_message);
+
+    E2eHandle* generator = e2e_new_x25519(E2E_AEAD_CHACHA20POLY1305);
+    E2eHandle* sender = e2e_new_x25519(E2E_AEAD_CHACHA20POLY1305);
+    E2eHandle* recipient = e2e_new_x25519(E2E_AEAD_CHACHA20POLY1305);
+    if (!generator || !sender || !recipient
+        || e2e_generate_pairkey(generator, priv_key_path, pub_key_path) != E2E_OK) {
+        printf("Failed to generate key pair: %s\n", e2e_last_error_message());
+        goto cleanup;
+    }
+
+    // Sender holds the public key of the recipient only
+    if (e2e_init_public(sender, pub_key_path) != E2E_OK
+        || e2e_encrypt(sender, (const unsigned char*)message, strlen(message), encrypted_message, &encrypted_message_len) != E2E_OK) {
+        printf("Failed to encrypt with a public key only: %s\n", e2e_last_error_message());
+        goto cleanup;
+    }
+    if (e2e_decrypt(sender, encrypted_message, encrypted_message_len, decrypted_message, &decrypted_message_len) != E2E_ERR_MISSING_PRIVATE_KEY) {
+        printf("Decrypt without private key should fail with E2E_ERR_MISSING_PRIVATE_KEY\n");
+        goto cleanup;
+    }
+
+    // Recipient holds its private key only
+    if (e2e_init_private(recipient, priv_key_path) != E2E_OK
+        || e2e_decrypt(recipient, encrypted_message, encrypted_message_len, decrypted_message, &decrypted_message_len) != E2E_OK
+        || decrypted_message_len != strlen(message) || memcmp(decrypted_message, message, decrypted_message_len) != 0) {
+        printf("Failed to decrypt with a private key only: %s\n", e2e_last_error_message());
+        goto cleanup;
+    }
+
+    printf("%s\n", message);
+    ret = 0;
+
+cleanup:
+    e2e_free(generator);
+    e2e_free(sender);
+    e2e_free(recipient);
+    remove(priv_key_path);
+    remove(pub_key_path);
+    return ret;
+}
+
+static int test_e2e_encrypt_multi(const char *out_dir) {
+    int ret = -1;
+    char priv_key_paths[2][BUFFER_SIZE];
+    char pub_key_paths[2][BUFFER_SIZE];
+    E2eHandle* owners[2] = {NULL, NULL};
+    E2eHandle* peers[2] = {NULL, NULL};
+    E2eHandle* x25519 = e2e_new_x25519(E2E_AEAD_CHACHA20POLY1305);
+    e2e_buffer encrypted = {NULL, 0};
+    e2e_buffer decrypted = {NULL, 0};
+    const char* message = "C wrapper test_e2e_encrypt_multi is passed";
+
+    // Each recipient owns a key pair, the sender holds their public keys only
+    for (int i = 0; i < 2; i++) {
+        snprintf(priv_key_paths[i], sizeof(priv_key_paths[i]), "%s/private_multi_%d.pem", out_dir, i);
+        snprintf(pub_key_paths[i], sizeof(pub_key_paths[i]), "%s/public_multi_%d.pem", out_dir, i);
+        owners[i] = e2e_new();
+        peers[i] = e2e_new();
+        if (!owners[i] || !peers[i]
+            || e2e_generate_pairkey(owners[i], priv_key_paths[i], pub_key_paths[i]) != E2E_OK
+            || e2e_init(owners[i], priv_key_paths[i], pub_key_paths[i]) != E2E_OK
+            || e2e_init_public(peers[i], pub_key_paths[i]) != E2E_OK) {
+            printf("Failed to provision recipient %d: %s\n", i, e2e_last_error_message());
+            goto cleanup;
+        }
+    }
+
+    if (e2e_encrypt_multi_alloc((const E2eHandle* const*)peers, 2, (const unsigned char*)message, strlen(message), &encrypted) != E2E_OK) {
+        printf("Failed to encrypt for several recipients: %s\n", e2e_last_error_message());
+        goto cleanup;
+    }
+    for (int i = 0; i < 2; i++) {
+        if (e2e_decrypt_alloc(owners[i], encrypted.data, encrypted.len, &decrypted) != E2E_OK
+            || decrypted.len != strlen(message) || memcmp(decrypted.data, message, decrypted.len) != 0) {
+            printf("Recipient %d failed to decrypt: %s\n", i, e2e_last_error_message());
+            goto cleanup;
+        }
+        e2e_buffer_free(&decrypted);
+    }
+
+    // Recipients must be RSA handles
+    const E2eHandle* mixed[2] = {peers[0], x25519};
+    e2e_buffer_free(&encrypted);
+    if (e2e_encrypt_multi_alloc(mixed, 2, (const unsigned char*)message, strlen(message), &encrypted) != E2E_ERR_UNSUPPORTED
+        || encrypted.data != NULL) {
+        printf("Non RSA recipient should fail with E2E_ERR_UNSUPPORTED\n");
+        goto cleanup;
+    }
+
+    printf("%s\n", message);
+    ret = 0;
+
+cleanup:
+    e2e_buffer_free(&encrypted);
+    e2e_buffer_free(&decrypted);
+    for (int i = 0; i < 2; i++) {
+        e2e_free(owners[i]);
+        e2e_free(peers[i]);
+        remove(priv_key_paths[i]);
+        remove(pub_key_paths[i]);
+    }
+    e2e_free(x25519);
+    return ret;
+}
+
+static int test_e2e_key_import_export(const char *out_dir) {
+    int ret = -1;
+    char priv_key_path[BUFFER_SIZE];
+    char pub_key_path[BUFFER_SIZE];
+    snprintf(priv_key_path, sizeof(priv_key_path), "%s/private_export.pem", out_dir);
+    snprintf(pub_key_path, sizeof(pub_key_path), "%s/public_export.pem", out_dir);
+
+    const char* message = "C wrapper test_e2e_key_import_export is passed";
+    e2e_buffer priv_key = {NULL, 0};
+    e2e_buffer pub_key = {NULL, 0};
+    e2e_buffer encrypted = {NULL, 0};
+    e2e_buffer decrypted = {NULL, 0};
+    E2eHandle* owner = e2e_new();
+    E2eHandle* imported = e2e_new();
+    E2eHandle* x25519 = e2e_new_x25519(E2E_AEAD_CHACHA20POLY1305);
+    if (!owner || !imported || !x25519
+        || e2e_generate_pairkey(owner, priv_key_path, pub_key_path) != E2E_OK
+        || e2e_init(owner, priv_key_path, pub_key_path) != E2E_OK) {
+        printf("Failed to provision keys: %s\n", e2e_last_error_message());
+        goto cleanup;
+    }
+
+    // Keys travel in memory, in a format other than the one of the key files
+    if (e2e_export_private_key_alloc(owner, E2E_KEY_FORMAT_PKCS1, E2E_KEY_ENCODING_DER, &priv_key) != E2E_OK
+        || e2e_export_public_key_alloc(owner, E2E_KEY_FORMAT_PKCS8, E2E_KEY_ENCODING_PEM, &pub_key) != E2E_OK
+        || e2e_init_from_bytes(imported, priv_key.data, priv_key.len, pub_key.data, pub_key.len) != E2E_OK) {
+        printf("Failed to export and import keys: %s\n", e2e_last_error_message());
+        goto cleanup;
+    }
+    if (e2e_encrypt_alloc(owner, (const unsigned char*)message, strlen(message), &encrypted) != E2E_OK
+        || e2e_decrypt_alloc(imported, encrypted.data, encrypted.len, &decrypted) != E2E_OK
+        || decrypted.len != strlen(message) || memcmp(decrypted.data, message, decrypted.len) != 0) {
+        printf("Imported keys failed to decrypt: %s\n", e2e_last_error_message());
+        goto cleanup;
+    }
+
+    // Uninitialized handle, unknown format and wrong key type are rejected
+    e2e_buffer_free(&pub_key);
+    if (e2e_export_public_key_alloc(x25519, E2E_KEY_FORMAT_PKCS8, E2E_KEY_ENCODING_PEM, &pub_key) != E2E_ERR_NOT_INITIALIZED
+        || e2e_export_public_key_alloc(owner, 7, E2E_KEY_ENCODING_PEM, &pub_key) != E2E_ERR_INVALID_ARGUMENT
+        || e2e_init_public_from_bytes(x25519, priv_key.data, priv_key.len) != E2E_ERR_MALFORMED_KEY) {
+        printf("Invalid export or import should fail\n");
+        goto cleanup;
+    }
+
+    printf("%s\n", message);
+    ret = 0;
+
+cleanup:
+    e2e_buffer_free(&priv_key);
+    e2e_buffer_free(&pub_key);
+    e2e_buffer_free(&encrypted);
+    e2e_buffer_free(&decrypted);
+    e2e_free(owner);
+    e2e_free(imported);
+    e2e_free(x25519);
+    remove(priv_key_path);
+    remove(pub_key_path);
+    return ret;
+}
+
+static int test_e2e_encrypted_pkcs8(const char *out_dir) {
+    int ret = -1;
+    char priv_key_path[BUFFER_SIZE];
+    char pub_key_path[BUFFER_SIZE];
+    snprintf(priv_key_path, sizeof(priv_key_path), "%s/private_pkcs8.pem", out_dir);
+    snprintf(pub_key_path, sizeof(pub_key_path), "%s/public_pkcs8.pem", out_dir);
+
+    const char* message = "C wrapper test_e2e_encrypted_pkcs8 is passed";
+    const char* password = "pkcs8 password";
+    e2e_buffer priv_key = {NULL, 0};
+    e2e_buffer encrypted = {NULL, 0};
+    e2e_buffer decrypted = {NULL, 0};
+    E2eHandle* owner = e2e_new_x25519(E2E_AEAD_CHACHA20POLY1305);
+    E2eHandle* imported = e2e_new_x25519(E2E_AEAD_CHACHA20POLY1305);
+    if (!owner || !imported
+        || e2e_generate_pairkey_encrypted_pkcs8(owner, priv_key_path, pub_key_path, password) != E2E_OK) {
+        printf("Failed to generate key pair: %s\n", e2e_last_error_message());
+        goto cleanup;
+    }
+    if (e2e_init_encrypted_pkcs8(owner, priv_key_path, pub_key_path, "wrong password") != E2E_ERR_WRONG_PASSWORD) {
+        printf("Wrong password should fail with E2E_ERR_WRONG_PASSWORD\n");
+        goto cleanup;
+    }
+    if (e2e_init_encrypted_pkcs8(owner, priv_key_path, pub_key_path, password) != E2E_OK) {
+        printf("Failed to init with encrypted PKCS#8 key: %s\n", e2e_last_error_message());
+        goto cleanup;
+    }
+
+    // Re-encrypted with PBKDF2 and imported from memory
+    if (e2e_export_encrypted_private_key_alloc(owner, E2E_KEY_ENCODING_DER, password, E2E_PKCS8_KDF_PBKDF2_SHA256, &priv_key) != E2E_OK
+        || e2e_init_private_from_encrypted_bytes(imported, priv_key.data, priv_key.len, password) != E2E_OK) {
+        printf("Failed to export and import encrypted key: %s\n", e2e_last_error_message());
+        goto cleanup;
+    }
+    if (e2e_encrypt_alloc(owner, (const unsigned char*)message, strlen(message), &encrypted) != E2E_OK
+        || e2e_decrypt_alloc(imported, encrypted.data, encrypted.len, &decrypted) != E2E_OK
+        || decrypted.len != strlen(message) || memcmp(decrypted.data, message, decrypted.len) != 0) {
+        printf("Imported key failed to decrypt: %s\n", e2e_last_error_message());
+        goto cleanup;
+    }
+
+    e2e_buffer_free(&priv_key);
+    if (e2e_export_encrypted_private_key_alloc(owner, E2E_KEY_ENCODING_PEM, password, 7, &priv_key) != E2E_ERR_INVALID_ARGUMENT) {
+        printf("Unknown KDF should fail with E2E_ERR_INVALID_ARGUMENT\n");
+        goto cleanup;
+    }
 
+    printf("%s\n", message);
+    ret = 0;
+
+cleanup:
+    e2e_buffer_free(&priv_key);
+    e2e_buffer_free(&encrypted);
+    e2e_buffer_free(&decrypted);
+    e2e_free(owner);
+    e2e_free(imported);
+    remove(priv_key_path);
+    remove(pub_key_path);
+    return ret;
+}
+
+static int test_e2e_migrate_exsecure_public_key(const char *out_dir) {
+    int ret = -1;
+    char priv_key_path[BUFFER_SIZE];
+    char pub_key_path[BUFFER_SIZE];
+    snprintf(priv_key_path, sizeof(priv_key_path), "%s/private_migrate.pem", out_dir);
+    snprintf(pub_key_path, sizeof(pub_key_path), "%s/public_migrate.pem", out_dir);
+    char legacy_priv_key_path[BUFFER_SIZE];
+    char legacy_pub_key_path[BUFFER_SIZE];
+    snprintf(legacy_priv_key_path, sizeof(legacy_priv_key_path), "%s/private_legacy.pem", out_dir);
+    snprintf(legacy_pub_key_path, sizeof(legacy_pub_key_path), "%s/public_legacy.pem", out_dir);
+
+    const char* message = "C wrapper test_e2e_migrate_exsecure_public_key is passed";
+    const char* password = "testpassword";
+    E2eHandle* owner = e2e_new_x25519(E2E_AEAD_CHACHA20POLY1305);
+    E2eHandle* peer = e2e_new_x25519(E2E_AEAD_CHACHA20POLY1305);
+    if (!owner || !peer
+        || e2e_generate_pairkey_withexsecure(owner, priv_key_path, pub_key_path, password) != E2E_OK) {
+        printf("Failed to generate key pair: %s\n", e2e_last_error_message());
+        goto cleanup;
+    }
+
+    // The public key of a new pair is plain
+    if (e2e_init_public(peer, pub_key_path) != E2E_OK
+        || e2e_init_withexsecure(owner, priv_key_path, pub_key_path, password) != E2E_OK) {
+        printf("Failed to use a plain public key: %s\n", e2e_last_error_message());
+        goto cleanup;
+    }
+
+    // The pair of the oldest versions, prepared by the test driver, is in the legacy headerless format
+    if (e2e_init_public(peer, legacy_pub_key_path) != E2E_ERR_MALFORMED_KEY
+        || e2e_init_withexsecure(owner, legacy_priv_key_path, legacy_pub_key_path, password) != E2E_OK) {
+        printf("Failed to use an encrypted public key: %s\n", e2e_last_error_message());
+        goto cleanup;
+    }
+    if (e2e_migrate_exsecure_public_key(legacy_priv_key_path, password) != E2E_ERR_INVALID_ARGUMENT) {
+        printf("Migrating a private key should fail with E2E_ERR_INVALID_ARGUMENT\n");
+        goto cleanup;
+    }
+    if (e2e_migrate_exsecure_public_key(legacy_pub_key_path, password) != E2E_OK
+        || e2e_init_public(peer, legacy_pub_key_path) != E2E_OK
+        || e2e_migrate_exsecure_public_key(legacy_pub_key_path, password) != E2E_OK) {
+        printf("Failed to migrate public key: %s\n", e2e_last_error_message());
+        goto cleanup;
+    }
+
+    printf("%s\n", message);
+    ret = 0;
+
+cleanup:
+    e2e_free(owner);
+    e2e_free(peer);
+    remove(priv_key_path);
+    remove(pub_key_path);
+    return ret;
+}
+
+//test 15
+static int test_e2e_migrate_legacy_folder(const char *out_dir) {
+    const char* password = "testpassword";
+    char folder_path[BUFFER_SIZE];
+    char file_path[BUFFER_SIZE];
+    snprintf(folder_path, sizeof(folder_path), "%s/test_migrate_legacy_folder", out_dir);
+    snprintf(file_path, sizeof(file_path), "%s/legacy1.txt", folder_path);
+
+    if (e2e_migrate_legacy_file(NULL, password) != E2E_ERR_INVALID_ARGUMENT
+        || e2e_migrate_legacy_folder(folder_path, NULL) != E2E_ERR_INVALID_ARGUMENT) {
+        printf("NULL arguments should be rejected\n");
+        return -1;
+    }
+
+    // Legacy files, prepared by the test driver, are skipped by e2e_decrypt_folder until they are migrated
+    if (e2e_migrate_legacy_file(file_path, password) != E2E_OK
+        || e2e_migrate_legacy_folder(folder_path, password) != E2E_OK
+        || e2e_decrypt_folder(folder_path, password) != E2E_OK) {
+        printf("Failed to migrate legacy folder: %s\n", e2e_last_error_message());
+        return -1;
+    }
+    printf("C wrapper test_e2e_migrate_legacy_folder is passed\n");
     return 0;
 }
 
@@ -146,6 +1033,76 @@ int main(int argc, char *argv[]) {
             printf("test_e2e_exsecure failed\n");
             return -1;
         }
+    } else if (number == 2) {
+        if (test_e2e_padding(out_dir)) {
+            printf("test_e2e_padding failed\n");
+            return -1;
+        }
+    } else if (number == 3) {
+        if (test_e2e_signature(out_dir)) {
+            printf("test_e2e_signature failed\n");
+            return -1;
+        }
+    } else if (number == 4) {
+        if (test_e2e_x25519(out_dir)) {
+            printf("test_e2e_x25519 failed\n");
+            return -1;
+        }
+    } else if (number == 5) {
+        if (test_e2e_folder_options(out_dir)) {
+            printf("test_e2e_folder_options failed\n");
+            return -1;
+        }
+    } else if (number == 6) {
+        if (test_e2e_null_safety(out_dir)) {
+            printf("test_e2e_null_safety failed\n");
+            return -1;
+        }
+    } else if (number == 7) {
+        if (test_e2e_buffer_size(out_dir)) {
+            printf("test_e2e_buffer_size failed\n");
+            return -1;
+        }
+    } else if (number == 8) {
+        if (test_e2e_alloc(out_dir)) {
+            printf("test_e2e_alloc failed\n");
+            return -1;
+        }
+    } else if (number == 9) {
+        if (test_e2e_log_callback(out_dir)) {
+            printf("test_e2e_log_callback failed\n");
+            return -1;
+        }
+    } else if (number == 10) {
+        if (test_e2e_init_public_private(out_dir)) {
+            printf("test_e2e_init_public_private failed\n");
+            return -1;
+        }
+    } else if (number == 11) {
+        if (test_e2e_encrypt_multi(out_dir)) {
+            printf("test_e2e_encrypt_multi failed\n");
+            return -1;
+        }
+    } else if (number == 12) {
+        if (test_e2e_key_import_export(out_dir)) {
+            printf("test_e2e_key_import_export failed\n");
+            return -1;
+        }
+    } else if (number == 13) {
+        if (test_e2e_encrypted_pkcs8(out_dir)) {
+            printf("test_e2e_encrypted_pkcs8 failed\n");
+            return -1;
+        }
+    } else if (number == 14) {
+        if (test_e2e_migrate_exsecure_public_key(out_dir)) {
+            printf("test_e2e_migrate_exsecure_public_key failed\n");
+            return -1;
+        }
+    } else if (number == 15) {
+        if (test_e2e_migrate_legacy_folder(out_dir)) {
+            printf("test_e2e_migrate_legacy_folder failed\n");
+            return -1;
+        }
     } else {
         return -1;
     }
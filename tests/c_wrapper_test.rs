@@ -1,26 +1,27 @@
 #[cfg(test)]
 mod tests {
-    use std::process::Command;
+    use std::process::{Command, Output};
     use std::env;
     use std::io::Write;
     use std::fs::{self, File};
     use std::path::{PathBuf, Path};
+    use std::sync::OnceLock;
 
     fn setup_test_dir(test_dir: &str) -> PathBuf {
         let path = Path::new(test_dir);
 
         //println!("Create test_folder: {:?}", path.to_path_buf());
-    
+
         if path.exists() {
             if let Err(e) = fs::remove_dir_all(path) {
                 eprintln!("Failed to remove directory: {}", e);
             }
         }
-    
+
         if let Err(e) = fs::create_dir(path) {
             eprintln!("Failed to create directory: {}", e);
         }
-    
+
         path.to_path_buf()
     }
 
@@ -32,6 +33,19 @@ mod tests {
         file_path
     }
 
+    // Encrypt in place with the legacy headerless format of the oldest versions
+    fn create_legacy_file(file_path: &Path, password: &str) {
+        use aes::cipher::{KeyIvInit, StreamCipher};
+        use sha2::{Digest, Sha256};
+
+        let hash = Sha256::digest(password.as_bytes());
+        let mut data = fs::read(file_path).unwrap();
+        data.extend_from_slice(&[0x11, 0x22, 0x33, 0x44]);
+        let mut cipher = ctr::Ctr64LE::<aes::Aes128>::new(hash[..16].into(), b"tranngochung1996".into());
+        cipher.apply_keystream(&mut data);
+        fs::write(file_path, &data).unwrap();
+    }
+
     fn cleanup_test_dir(test_dir: &str) {
         let path = Path::new(test_dir);
         if path.exists() {
@@ -39,66 +53,189 @@ mod tests {
         }
     }
 
+    // Compile tests/c_wrapper_test.c once, the tests running in parallel share the binary
+    fn c_test_binary() -> &'static Path {
+        static BINARY: OnceLock<PathBuf> = OnceLock::new();
+        BINARY.get_or_init(|| {
+            let binary = PathBuf::from(env::var("OUT_DIR").unwrap()).join("c_wrapper_test");
+            let status = Command::new("gcc")
+                .args([
+                    "-o", &format!("{}", binary.display()),
+                    "tests/c_wrapper_test.c",
+                    "-L", "target/debug",
+                    "-le2esdk",
+                    "-Iinclude",
+                ])
+                .status()
+                .expect("Failed to compile C test");
+
+            assert!(status.success());
+            binary
+        })
+    }
+
+    // Directory given to the C test as out_dir, one per mode so modes never share files
+    fn c_test_dir(mode: u32) -> PathBuf {
+        let test_dir = PathBuf::from(env::var("OUT_DIR").unwrap()).join(format!("c_wrapper_test_{}", mode));
+        fs::create_dir_all(&test_dir).unwrap();
+        test_dir
+    }
+
+    // Run one mode of the C test in c_test_dir(mode), it must succeed
+    fn run_c_test(mode: u32) -> Output {
+        let output = Command::new(c_test_binary())
+            .arg(c_test_dir(mode))
+            .arg(mode.to_string())
+            .output()
+            .expect("Failed to execute test");
+
+        assert!(
+            output.status.success(),
+            "C test mode {} failed:\n{}{}",
+            mode,
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        output
+    }
+
     #[test]
     fn test_c_wrapper_basic_functions() {
-        let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
-        //println!("running commands from dir {:?}", out_dir);
-        let status = Command::new("gcc")
-            .args(&[
-                "-o", &format!("{}/c_wrapper_test", out_dir.display()),
-                "tests/c_wrapper_test.c",
-                "-L", "target/debug",
-                "-le2esdk",
-                "-Iinclude",
-            ])
-            .status()
-            .expect("Failed to compile C test");
-
-        assert!(status.success());
-
-        let status = Command::new(format!("{}/c_wrapper_test", out_dir.display()))
-            .args(&[
-                &format!("{}", out_dir.display()),
-                "0",
-            ])
-            .status()
-            .expect("Failed to execute test");
+        run_c_test(0);
+    }
 
-        assert!(status.success());
+    #[test]
+    fn test_c_wrapper_padding() {
+        run_c_test(2);
     }
 
     #[test]
-    fn test_c_wrapper_exsecure() {
-        let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
-        //println!("running commands from dir {:?}", out_dir);
-        let status = Command::new("gcc")
-            .args(&[
-                "-o", &format!("{}/c_wrapper_test", out_dir.display()),
-                "tests/c_wrapper_test.c",
-                "-L", "target/debug",
-                "-le2esdk",
-                "-Iinclude",
-            ])
-            .status()
-            .expect("Failed to compile C test");
-
-        assert!(status.success());
+    fn test_c_wrapper_signature() {
+        run_c_test(3);
+    }
+
+    #[test]
+    fn test_c_wrapper_x25519() {
+        run_c_test(4);
+    }
+
+    #[test]
+    fn test_c_wrapper_null_safety() {
+        run_c_test(6);
+    }
 
+    #[test]
+    fn test_c_wrapper_buffer_size() {
+        run_c_test(7);
+    }
+
+    #[test]
+    fn test_c_wrapper_exsecure() {
         //const PASSWORD: &str = "testpassword";
-        const TEST_DIR: &str = "test_folder";
-        let _ = setup_test_dir(&format!("{}/{}", out_dir.display(), TEST_DIR));
-        let _ = create_test_file(&format!("{}/{}", out_dir.display(), TEST_DIR), "test1.txt", b"Hello world! Im robot1");
-        let _ = create_test_file(&format!("{}/{}", out_dir.display(), TEST_DIR), "test2.txt", b"Hello world! Im robot2");
-
-        let status = Command::new(format!("{}/c_wrapper_test", out_dir.display()))
-            .args(&[
-                &format!("{}", out_dir.display()),
-                "1",
-            ])
-            .status()
-            .expect("Failed to execute test");
+        let test_dir = format!("{}/test_folder", c_test_dir(1).display());
+        let _ = setup_test_dir(&test_dir);
+        let _ = create_test_file(&test_dir, "test1.txt", b"Hello world! Im robot1");
+        let _ = create_test_file(&test_dir, "test2.txt", b"Hello world! Im robot2");
+
+        run_c_test(1);
+        cleanup_test_dir(&test_dir);
+    }
+
+    #[test]
+    fn test_c_wrapper_folder_options() {
+        let test_dir = format!("{}/test_folder_options", c_test_dir(5).display());
+        let root = setup_test_dir(&test_dir);
+        fs::create_dir_all(root.join("tenant/deep")).unwrap();
+        fs::create_dir_all(root.join("skip")).unwrap();
+        let top_key = create_test_file(&test_dir, "a.pem", b"top level key");
+        let notes = create_test_file(&test_dir, "notes.txt", b"not a key");
+        let tenant_key = create_test_file(&format!("{}/tenant", test_dir), "b.pem", b"tenant key");
+        let deep_key = create_test_file(&format!("{}/tenant/deep", test_dir), "c.pem", b"too deep key");
+        let skipped_key = create_test_file(&format!("{}/skip", test_dir), "d.pem", b"excluded key");
+
+        run_c_test(5);
+
+        let encrypted: Vec<bool> = [&top_key, &notes, &tenant_key, &deep_key, &skipped_key]
+            .iter()
+            .map(|path| e2esdk::is_encrypted_file(path).unwrap())
+            .collect();
+        cleanup_test_dir(&test_dir);
+        assert_eq!(encrypted, [true, false, true, false, false]);
+    }
+
+    #[test]
+    fn test_c_wrapper_alloc() {
+        // Binary content with zero bytes, the C side compares it with the decrypted data
+        let file_path = create_test_file(&format!("{}", c_test_dir(8).display()), "alloc_test.bin", b"key\x00\x01\x02\x00end");
+        e2esdk::encrypt_file(&file_path, "testpassword").unwrap();
+
+        run_c_test(8);
+        fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn test_c_wrapper_log_callback() {
+        let output = run_c_test(9);
+
+        // The library never writes to the output of the host application
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(!stdout.contains("missing.bin") && !stderr.contains("missing.bin"), "{}{}", stdout, stderr);
+    }
+
+    #[test]
+    fn test_c_wrapper_init_public_private() {
+        run_c_test(10);
+    }
+
+    #[test]
+    fn test_c_wrapper_encrypt_multi() {
+        run_c_test(11);
+    }
+
+    #[test]
+    fn test_c_wrapper_key_import_export() {
+        run_c_test(12);
+    }
+
+    #[test]
+    fn test_c_wrapper_encrypted_pkcs8() {
+        run_c_test(13);
+    }
+
+    #[test]
+    fn test_c_wrapper_migrate_exsecure_public_key() {
+        // Key pair of the oldest versions, both keys are in the legacy headerless AES-128-CTR format
+        let test_dir = c_test_dir(14);
+        let priv_key_path = test_dir.join("private_legacy.pem");
+        let pub_key_path = test_dir.join("public_legacy.pem");
+        use e2esdk::E2eCyber;
+        let mut e2e_x25519 = e2esdk::E2eX25519::new();
+        e2e_x25519.generate_pairkey(&priv_key_path, &pub_key_path).unwrap();
+        create_legacy_file(&priv_key_path, "testpassword");
+        create_legacy_file(&pub_key_path, "testpassword");
+
+        run_c_test(14);
+        fs::remove_file(&priv_key_path).unwrap();
+        fs::remove_file(&pub_key_path).unwrap();
+    }
+
+    #[test]
+    fn test_c_wrapper_migrate_legacy_folder() {
+        let test_dir = format!("{}/test_migrate_legacy_folder", c_test_dir(15).display());
+        setup_test_dir(&test_dir);
+        let legacy_files = [
+            create_test_file(&test_dir, "legacy1.txt", b"first legacy file"),
+            create_test_file(&test_dir, "legacy2.txt", b"second legacy file"),
+        ];
+        for file_path in &legacy_files {
+            create_legacy_file(file_path, "testpassword");
+        }
+
+        run_c_test(15);
 
-        let _ = cleanup_test_dir(&format!("{}/{}", out_dir.display(), TEST_DIR));
-        assert!(status.success());
+        let contents: Vec<Vec<u8>> = legacy_files.iter().map(|path| fs::read(path).unwrap()).collect();
+        cleanup_test_dir(&test_dir);
+        assert_eq!(contents, [b"first legacy file".to_vec(), b"second legacy file".to_vec()]);
     }
-}
\ No newline at end of file
+}